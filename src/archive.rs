@@ -0,0 +1,50 @@
+//! Per-list archives of completed todos, kept apart from the lists by the
+//! storage backend so they stay out of `rustodo list`.
+
+use crate::date;
+use crate::error::Result;
+use crate::storage::Storage;
+use crate::todo::{Todo, TodoList};
+
+/// Moves the completed todos of a list into its archive and saves both.
+/// Returns the number of archived todos.
+pub fn archive_completed(list: &mut TodoList, storage: &dyn Storage) -> Result<usize> {
+    let completed = list.todos.iter().filter(|t| t.done).cloned().collect();
+    archive(list, completed, storage)
+}
+
+/// Moves todos into the archive of a list, removing the todos with the same
+/// ids from the list, and saves both. Returns the number of archived todos.
+pub fn archive(list: &mut TodoList, todos: Vec<Todo>, storage: &dyn Storage) -> Result<usize> {
+    let mut archive = storage.load_archive(&list.name)?;
+    let now = date::now();
+    let count = todos.len();
+
+    list.todos
+        .retain(|todo| !todos.iter().any(|t| t.id == todo.id));
+    for mut todo in todos {
+        todo.completed_at.get_or_insert(now);
+        archive.todos.push(todo);
+    }
+
+    // The archive is written first, so a failure can't lose any todos
+    storage.save_archive(&archive)?;
+    storage.save(list)?;
+
+    Ok(count)
+}
+
+/// Moves an archived todo back into its list and saves both.
+pub fn restore(
+    list: &mut TodoList,
+    archive: &mut TodoList,
+    index: usize,
+    storage: &dyn Storage,
+) -> Result<()> {
+    let mut todo = archive.todos.remove(index);
+    todo.id = list.next_id();
+    list.todos.push(todo);
+
+    storage.save(list)?;
+    storage.save_archive(archive)
+}
@@ -0,0 +1,102 @@
+//! Where the panel reads keys from and draws to: the terminal, or nothing
+//! at all when keys are replayed, see `replay`.
+
+use crate::draw;
+use crate::panel::Event;
+use crate::reader::Reader;
+use crate::terminal;
+use std::io::{stdout, Stdout, Write};
+use std::sync::mpsc::Sender;
+use termion::event::Key;
+use termion::raw::{IntoRawMode, RawTerminal};
+use termion::terminal_size;
+
+pub trait Backend {
+    /// Starts sending input to the panel.
+    fn listen(&mut self);
+
+    fn size(&self) -> (u16, u16);
+
+    /// Writes output of the renderer.
+    fn write(&mut self, out: &str);
+
+    /// Takes the terminal again after it was given back, e.g. to suspend.
+    fn enter(&mut self);
+}
+
+/// The terminal the panel was started in, in raw mode and on the alternate
+/// screen.
+pub struct Tty {
+    stdout: RawTerminal<Stdout>,
+    reader: Reader,
+}
+
+impl Tty {
+    pub fn new(events: Sender<Event>) -> Self {
+        terminal::install(events.clone());
+        let mut stdout = stdout().into_raw_mode().unwrap();
+        // The user's scrollback is shown again once the panel quits
+        write!(stdout, "{}", draw::alternate_screen()).unwrap();
+        Tty {
+            stdout,
+            reader: Reader::new(events),
+        }
+    }
+}
+
+impl Backend for Tty {
+    fn listen(&mut self) {
+        self.reader.listen_events();
+    }
+
+    fn size(&self) -> (u16, u16) {
+        terminal_size().unwrap()
+    }
+
+    fn write(&mut self, out: &str) {
+        self.stdout.write_all(out.as_bytes()).unwrap();
+        self.stdout.flush().unwrap();
+    }
+
+    fn enter(&mut self) {
+        self.stdout.activate_raw_mode().unwrap();
+        write!(self.stdout, "{}", draw::alternate_screen()).unwrap();
+    }
+}
+
+/// A screen of a fixed size that only exists in the panel's frame. It sends
+/// its keys once the panel listens, followed by `Event::Closed`.
+pub struct Headless {
+    width: u16,
+    height: u16,
+    keys: Vec<Key>,
+    events: Sender<Event>,
+}
+
+impl Headless {
+    pub fn new(width: u16, height: u16, keys: Vec<Key>, events: Sender<Event>) -> Self {
+        Headless {
+            width,
+            height,
+            keys,
+            events,
+        }
+    }
+}
+
+impl Backend for Headless {
+    fn listen(&mut self) {
+        for key in self.keys.drain(..) {
+            self.events.send(Event::KeyPressed(key)).ok();
+        }
+        self.events.send(Event::Closed).ok();
+    }
+
+    fn size(&self) -> (u16, u16) {
+        (self.width, self.height)
+    }
+
+    fn write(&mut self, _out: &str) {}
+
+    fn enter(&mut self) {}
+}
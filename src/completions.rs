@@ -0,0 +1,218 @@
+//! Shell completion scripts, printed by `rustodo completions <shell>`. List
+//! names are completed by calling `rustodo __lists`, so they follow the lists
+//! in the todo path.
+
+const BASH: &str = r#"# bash completion for rustodo, load with:
+#   source <(rustodo completions bash)
+_rustodo_lists() {
+    local IFS=$'\n'
+    COMPREPLY+=($(compgen -W "$(rustodo __lists 2>/dev/null)" -- "$1"))
+}
+
+_rustodo() {
+    local cur="${COMP_WORDS[COMP_CWORD]}"
+    local prev="${COMP_WORDS[COMP_CWORD-1]}"
+    local command="${COMP_WORDS[1]}"
+    COMPREPLY=()
+
+    if [ "$COMP_CWORD" -eq 1 ]; then
+        COMPREPLY=($(compgen -W "@COMMANDS@ --replay" -- "$cur"))
+        _rustodo_lists "$cur"
+        return
+    fi
+
+    case "$prev" in
+        --sort) COMPREPLY=($(compgen -W "@SORTS@" -- "$cur")); return ;;
+        --format) COMPREPLY=($(compgen -W "@FORMATS@" -- "$cur")); return ;;
+        --columns) COMPREPLY=($(compgen -W "@COLUMNS@" -- "$cur")); return ;;
+        --socket) COMPREPLY=($(compgen -f -- "$cur")); return ;;
+        --dump) COMPREPLY=($(compgen -W "@DUMPS@" -- "$cur")); return ;;
+        --port|--origin|--tag) return ;;
+    esac
+
+    case "$command" in
+        list) COMPREPLY=($(compgen -W "--sort --json" -- "$cur")) ;;
+        find) COMPREPLY=($(compgen -W "--tag --open --done" -- "$cur")) ;;
+        serve) COMPREPLY=($(compgen -W "--port --origin" -- "$cur")) ;;
+        daemon) COMPREPLY=($(compgen -W "--socket" -- "$cur")) ;;
+        completions) COMPREPLY=($(compgen -W "@SHELLS@" -- "$cur")) ;;
+        import)
+            [ "$COMP_CWORD" -eq 2 ] && COMPREPLY=($(compgen -f -- "$cur")) ;;
+        export)
+            COMPREPLY=($(compgen -W "--all --format --columns" -- "$cur"))
+            _rustodo_lists "$cur" ;;
+        rm)
+            COMPREPLY=($(compgen -W "--yes" -- "$cur"))
+            _rustodo_lists "$cur" ;;
+        mv)
+            [ "$COMP_CWORD" -eq 2 ] || [ "$COMP_CWORD" -eq 4 ] && _rustodo_lists "$cur" ;;
+        archive|rename|cp|reset|stats|history)
+            [ "$COMP_CWORD" -eq 2 ] && _rustodo_lists "$cur" ;;
+        --replay)
+            case "$COMP_CWORD" in
+                2) COMPREPLY=($(compgen -f -- "$cur")) ;;
+                3) _rustodo_lists "$cur" ;;
+                *) COMPREPLY=($(compgen -W "--dump" -- "$cur")) ;;
+            esac ;;
+    esac
+}
+
+complete -F _rustodo rustodo
+"#;
+
+const ZSH: &str = r#"#compdef rustodo
+# zsh completion for rustodo, load with:
+#   source <(rustodo completions zsh)
+_rustodo_lists() {
+    local -a lists
+    lists=("${(@f)$(rustodo __lists 2>/dev/null)}")
+    _describe 'list' lists
+}
+
+_rustodo() {
+    local -a commands
+    commands=(@ZSH_COMMANDS@ '--replay:@REPLAY@')
+
+    if (( CURRENT == 2 )); then
+        _describe 'command' commands
+        _rustodo_lists
+        return
+    fi
+
+    # Arguments are counted from the subcommand
+    local command="$words[2]"
+    shift words
+    (( CURRENT-- ))
+
+    case "$command" in
+        list) _arguments '--sort[sort the lists]:sort:(@SORTS@)' '--json[print json]' ;;
+        find) _arguments '--tag[todos with a tag]:tag:' '--open[open todos]' '--done[done todos]' '1::text:' ;;
+        export)
+            _arguments '--all[export every list]' '--format[export format]:format:(@FORMATS@)' \
+                '--columns[csv columns]:columns:(@COLUMNS@)' '1:list:_rustodo_lists' '2::file:_files' ;;
+        import) _arguments '1:file:_files' '2::list:' ;;
+        rm) _arguments '--yes[remove without asking]' '1:list:_rustodo_lists' ;;
+        mv) _arguments '1:list:_rustodo_lists' '2:id:' '3:target list:_rustodo_lists' ;;
+        rename|cp) _arguments '1:list:_rustodo_lists' '2:new name:' ;;
+        archive|reset|history) _arguments '1:list:_rustodo_lists' ;;
+        stats) _arguments '1::list:_rustodo_lists' ;;
+        serve) _arguments '--port[port to listen on]:port:' '*--origin[origin allowed to use the api]:origin:' ;;
+        daemon) _arguments '--socket[socket to listen on]:socket:_files' ;;
+        completions) _arguments '1:shell:(@SHELLS@)' ;;
+        --replay)
+            _arguments '--dump[print the screen or the list]:dump:(@DUMPS@)' \
+                '1:keys file:_files' '2:list:_rustodo_lists' ;;
+    esac
+}
+
+if [ "$funcstack[1]" = "_rustodo" ]; then
+    _rustodo "$@"
+else
+    compdef _rustodo rustodo
+fi
+"#;
+
+const FISH: &str = r#"# fish completion for rustodo, load with:
+#   rustodo completions fish | source
+function __rustodo_lists
+    rustodo __lists 2>/dev/null
+end
+
+complete -c rustodo -f
+complete -c rustodo -n __fish_use_subcommand -a '(__rustodo_lists)' -d 'Open a list'
+@FISH_COMMANDS@
+complete -c rustodo -n '__fish_seen_subcommand_from archive rename rm cp reset mv stats history export' -a '(__rustodo_lists)'
+complete -c rustodo -n '__fish_seen_subcommand_from import' -F
+complete -c rustodo -n '__fish_seen_subcommand_from list' -l sort -x -a '@SORTS@'
+complete -c rustodo -n '__fish_seen_subcommand_from list' -l json
+complete -c rustodo -n '__fish_seen_subcommand_from find' -l tag -x
+complete -c rustodo -n '__fish_seen_subcommand_from find' -l open
+complete -c rustodo -n '__fish_seen_subcommand_from find' -l done
+complete -c rustodo -n '__fish_seen_subcommand_from export' -l all
+complete -c rustodo -n '__fish_seen_subcommand_from export' -l format -x -a '@FORMATS@'
+complete -c rustodo -n '__fish_seen_subcommand_from export' -l columns -x -a '@COLUMNS@'
+complete -c rustodo -n '__fish_seen_subcommand_from rm' -s y -l yes
+complete -c rustodo -n '__fish_seen_subcommand_from serve' -l port -x
+complete -c rustodo -n '__fish_seen_subcommand_from serve' -l origin -x
+complete -c rustodo -n '__fish_seen_subcommand_from daemon' -l socket -r -F
+complete -c rustodo -n '__fish_seen_subcommand_from completions' -a '@SHELLS@'
+complete -c rustodo -n __fish_use_subcommand -l replay -d '@REPLAY@'
+complete -c rustodo -n '__fish_contains_opt replay' -F -a '(__rustodo_lists)'
+complete -c rustodo -n '__fish_contains_opt replay' -l dump -x -a '@DUMPS@'
+"#;
+
+/// Subcommands and what they do.
+const COMMANDS: [(&str, &str); 17] = [
+    ("new", "Create a list"),
+    ("list", "Show all lists"),
+    ("import", "Import a todo.txt or iCalendar file"),
+    ("export", "Export lists"),
+    ("archive", "Archive the completed todos of a list"),
+    ("stats", "Show completion statistics"),
+    ("find", "Find todos in all lists"),
+    ("mv", "Move a todo into another list"),
+    ("rename", "Rename a list"),
+    ("rm", "Remove a list"),
+    ("cp", "Copy a list"),
+    ("reset", "Uncheck every todo of a list"),
+    ("sync", "Pull and push the git remote"),
+    ("history", "Show the git history of a list"),
+    ("serve", "Serve the lists over http"),
+    ("daemon", "Serve JSON-RPC on a unix socket"),
+    ("completions", "Print a shell completion script"),
+];
+
+const SHELLS: &str = "bash zsh fish";
+
+/// `--replay <keys file> <list> [--dump screen|list]`, which isn't a
+/// subcommand but is completed like one.
+const REPLAY: &str = "Replay keys from a file on a list";
+
+pub fn script(shell: &str) -> Result<String, String> {
+    let template = match shell {
+        "bash" => BASH,
+        "zsh" => ZSH,
+        "fish" => FISH,
+        other => {
+            return Err(format!(
+                "Unknown shell '{}' (expected {})",
+                other,
+                SHELLS.replace(' ', ", ")
+            ))
+        }
+    };
+
+    let names: Vec<&str> = COMMANDS.iter().map(|(name, _)| *name).collect();
+    let zsh_commands: Vec<String> = COMMANDS
+        .iter()
+        .map(|(name, description)| format!("'{}:{}'", name, description))
+        .collect();
+    let fish_commands: Vec<String> = COMMANDS
+        .iter()
+        .map(|(name, description)| {
+            format!(
+                "complete -c rustodo -n __fish_use_subcommand -a {} -d '{}'",
+                name, description
+            )
+        })
+        .collect();
+
+    Ok(template
+        .replace("@COMMANDS@", &names.join(" "))
+        .replace("@ZSH_COMMANDS@", &zsh_commands.join(" "))
+        .replace("@FISH_COMMANDS@", &fish_commands.join("\n"))
+        .replace("@SORTS@", "name progress modified open")
+        .replace("@FORMATS@", "txt csv jsonl ics")
+        .replace("@COLUMNS@", &column_names())
+        .replace("@SHELLS@", SHELLS)
+        .replace("@REPLAY@", REPLAY)
+        .replace("@DUMPS@", "screen list"))
+}
+
+fn column_names() -> String {
+    rustodo::export::Column::ALL
+        .iter()
+        .map(|column| column.name())
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
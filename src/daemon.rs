@@ -0,0 +1,483 @@
+//! `rustodo daemon`, JSON-RPC 2.0 over a Unix socket for editor
+//! integrations. Messages are single lines of json. The methods mirror the
+//! operations of the panel:
+//!
+//! - `lists`: names of all lists
+//! - `get {list}`: a list, as it is stored
+//! - `create {list, item, priority?, tags?, done?, due_at?}`: adds a todo
+//! - `update {list, id, item?, priority?, tags?, done?, due_at?}`
+//! - `toggle {list, id}`, `delete {list, id}`
+//! - `move {list, id, target}`: moves a todo into another list
+//! - `save {list}`: replaces a whole list, as sent by `get`
+//! - `subscribe {lists?}`, `unsubscribe`: `changed {list}` notifications for
+//!   the given lists, or all lists
+//!
+//! Connections are read and written on their own threads, but requests are
+//! handled one at a time on the calling thread, which owns the storage.
+//! Lists changed elsewhere, e.g. in the panel or by `rustodo sync`, are
+//! noticed by polling `Storage::watch_path`. Every list of the sqlite backend
+//! has the same path, so a change to one notifies about all of them.
+
+use std::collections::{HashMap, HashSet};
+use std::io::{BufRead, BufReader, Write};
+use std::net::Shutdown;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::Path;
+use std::sync::mpsc::{channel, sync_channel, RecvTimeoutError, Sender, SyncSender, TrySendError};
+use std::time::{Duration, Instant, SystemTime};
+use std::{fs, io, thread};
+
+use rustodo::storage::Storage;
+use rustodo::{store, Error, TodoChanges, TodoList};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use signal_hook::consts::{SIGHUP, SIGINT, SIGTERM};
+use signal_hook::iterator::Signals;
+
+pub const DEFAULT_SOCKET: &str = ".rustodo.sock";
+
+/// How often the lists are checked for changes made elsewhere.
+const WATCH_INTERVAL: Duration = Duration::from_millis(500);
+/// Messages waiting for a client before it is disconnected for not reading
+/// them.
+const OUTBOX_SIZE: usize = 256;
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+/// Application errors, see `Failure::from`.
+const FAILED: i64 = -32000;
+const NOT_FOUND: i64 = -32001;
+const CONFLICT: i64 = -32002;
+
+enum Incoming {
+    Connected(u64, UnixStream),
+    Message(u64, String),
+    Closed(u64),
+    /// SIGINT, SIGTERM or SIGHUP was received.
+    Stopped,
+}
+
+struct Client {
+    /// Messages for the writer thread of the client, so a client that stops
+    /// reading doesn't hold up the others.
+    outbox: SyncSender<Value>,
+    stream: UnixStream,
+    /// Lists the client is notified about once it subscribed, all lists if
+    /// it is empty.
+    subscription: Option<HashSet<String>>,
+}
+
+impl Client {
+    fn new(stream: UnixStream) -> io::Result<Self> {
+        let mut writer = stream.try_clone()?;
+        let (outbox, messages) = sync_channel::<Value>(OUTBOX_SIZE);
+        thread::spawn(move || {
+            for message in messages {
+                if writeln!(writer, "{}", message).is_err() {
+                    return;
+                }
+            }
+        });
+        Ok(Client {
+            outbox,
+            stream,
+            subscription: None,
+        })
+    }
+
+    fn send(&self, message: Value) {
+        // The reader stops once the stream is shut down, which removes the
+        // client. A client that is gone is removed the same way.
+        if let Err(TrySendError::Full(_)) = self.outbox.try_send(message) {
+            self.stream.shutdown(Shutdown::Both).ok();
+        }
+    }
+}
+
+/// Modification time and size of the file of each list when the lists were
+/// last checked, see `changed_lists`.
+type Stamps = HashMap<String, Option<(Option<SystemTime>, u64)>>;
+
+/// A JSON-RPC error, with its code and message.
+struct Failure(i64, String);
+
+impl From<Error> for Failure {
+    fn from(err: Error) -> Self {
+        let code = match err {
+            Error::ListNotFound(_) | Error::TodoNotFound { .. } => NOT_FOUND,
+            Error::ListExists(_) | Error::SameList(_) | Error::AmbiguousList { .. } => CONFLICT,
+            Error::InvalidTodo(_) | Error::InvalidName(_) => INVALID_PARAMS,
+            _ => FAILED,
+        };
+        Failure(code, err.to_string())
+    }
+}
+
+#[derive(Deserialize)]
+struct ListParams {
+    list: String,
+}
+
+#[derive(Deserialize)]
+struct TodoParams {
+    list: String,
+    id: i16,
+}
+
+#[derive(Deserialize)]
+struct ChangeParams {
+    list: String,
+    id: Option<i16>,
+    #[serde(flatten)]
+    changes: Value,
+}
+
+#[derive(Deserialize)]
+struct MoveParams {
+    list: String,
+    id: i16,
+    target: String,
+}
+
+#[derive(Deserialize, Default)]
+struct SubscribeParams {
+    lists: Option<Vec<String>>,
+}
+
+pub fn run(storage: &dyn Storage, path: &str) -> Result<(), String> {
+    // A socket left by a daemon that is gone refuses connections
+    if Path::new(path).exists() {
+        if UnixStream::connect(path).is_ok() {
+            return Err(format!("A daemon is already listening on '{}'", path));
+        }
+        if let Err(err) = fs::remove_file(path) {
+            return Err(format!("Unable to remove '{}': {}", path, err));
+        }
+    }
+    let mut signals = match Signals::new([SIGINT, SIGTERM, SIGHUP]) {
+        Ok(signals) => signals,
+        Err(err) => return Err(format!("Unable to handle signals: {}", err)),
+    };
+    let listener = match UnixListener::bind(path) {
+        Ok(listener) => listener,
+        Err(err) => return Err(format!("Unable to listen on '{}': {}", path, err)),
+    };
+    println!("Listening on {}", path);
+
+    let (sender, receiver) = channel();
+    let stopped = sender.clone();
+    thread::spawn(move || {
+        if signals.forever().next().is_some() {
+            stopped.send(Incoming::Stopped).ok();
+        }
+    });
+    thread::spawn(move || accept(listener, sender));
+
+    let mut clients: HashMap<u64, Client> = HashMap::new();
+    let mut stamps = Stamps::new();
+    changed_lists(storage, &mut stamps);
+    let mut next_check = Instant::now() + WATCH_INTERVAL;
+    loop {
+        let timeout = next_check.saturating_duration_since(Instant::now());
+        match receiver.recv_timeout(timeout) {
+            Ok(Incoming::Connected(id, stream)) => {
+                match Client::new(stream) {
+                    Ok(client) => {
+                        clients.insert(id, client);
+                    }
+                    Err(err) => eprintln!("Unable to answer a client: {}", err),
+                };
+            }
+            Ok(Incoming::Closed(id)) => {
+                clients.remove(&id);
+            }
+            Ok(Incoming::Message(id, line)) => {
+                let (response, changed) = respond(storage, &mut clients, id, &line);
+                if let (Some(response), Some(client)) = (response, clients.get(&id)) {
+                    client.send(response);
+                }
+                if !changed.is_empty() {
+                    // Notified right away rather than once they are polled
+                    changed_lists(storage, &mut stamps);
+                }
+                for list in changed {
+                    notify(&clients, &list);
+                }
+            }
+            Ok(Incoming::Stopped) | Err(RecvTimeoutError::Disconnected) => break,
+            Err(RecvTimeoutError::Timeout) => {}
+        }
+
+        if Instant::now() >= next_check {
+            for list in changed_lists(storage, &mut stamps) {
+                notify(&clients, &list);
+            }
+            next_check = Instant::now() + WATCH_INTERVAL;
+        }
+    }
+
+    if let Err(err) = fs::remove_file(path) {
+        return Err(format!("Unable to remove '{}': {}", path, err));
+    }
+    Ok(())
+}
+
+/// The lists that were changed, created or removed since the last check.
+fn changed_lists(storage: &dyn Storage, stamps: &mut Stamps) -> Vec<String> {
+    let names = match storage.names() {
+        Ok(names) => names,
+        // Checked again on the next poll
+        Err(_) => return Vec::new(),
+    };
+
+    let mut current = Stamps::new();
+    for name in names {
+        let stamp = storage
+            .watch_path(&name)
+            .and_then(|path| fs::metadata(path).ok())
+            .map(|metadata| (metadata.modified().ok(), metadata.len()));
+        current.insert(name, stamp);
+    }
+
+    let mut changed: Vec<String> = current
+        .iter()
+        .filter(|(name, stamp)| stamps.get(*name) != Some(stamp))
+        .map(|(name, _)| name.clone())
+        .collect();
+    changed.extend(
+        stamps
+            .keys()
+            .filter(|name| !current.contains_key(*name))
+            .cloned(),
+    );
+    changed.sort();
+    *stamps = current;
+    changed
+}
+
+fn accept(listener: UnixListener, sender: Sender<Incoming>) {
+    for (id, stream) in listener.incoming().flatten().enumerate() {
+        let id = id as u64;
+        let writer = match stream.try_clone() {
+            Ok(writer) => writer,
+            Err(_) => continue,
+        };
+        if sender.send(Incoming::Connected(id, writer)).is_err() {
+            return;
+        }
+
+        let sender = sender.clone();
+        thread::spawn(move || {
+            for line in BufReader::new(stream).lines() {
+                match line {
+                    Ok(line) if line.trim().is_empty() => continue,
+                    Ok(line) => {
+                        if sender.send(Incoming::Message(id, line)).is_err() {
+                            return;
+                        }
+                    }
+                    Err(_) => break,
+                }
+            }
+            sender.send(Incoming::Closed(id)).ok();
+        });
+    }
+}
+
+/// Handles one message. Returns the response, which notifications don't
+/// get, and the lists that were changed.
+fn respond(
+    storage: &dyn Storage,
+    clients: &mut HashMap<u64, Client>,
+    client: u64,
+    line: &str,
+) -> (Option<Value>, Vec<String>) {
+    let request: Value = match serde_json::from_str(line) {
+        Ok(request) => request,
+        Err(err) => return (Some(error(Value::Null, PARSE_ERROR, err)), Vec::new()),
+    };
+    let id = request.get("id").cloned();
+    let method = match request.get("method").and_then(Value::as_str) {
+        Some(method) if request.get("jsonrpc") == Some(&json!("2.0")) => method,
+        _ => {
+            let message = "Expected a JSON-RPC 2.0 request";
+            return (
+                Some(error(id.unwrap_or(Value::Null), INVALID_REQUEST, message)),
+                Vec::new(),
+            );
+        }
+    };
+    let params = request.get("params").cloned().unwrap_or(Value::Null);
+
+    let (reply, changed) = match call(storage, clients, client, method, params) {
+        Ok((result, changed)) => (Ok(result), changed),
+        Err(failure) => (Err(failure), Vec::new()),
+    };
+    let response = id.map(|id| match reply {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(Failure(code, message)) => error(id, code, message),
+    });
+    (response, changed)
+}
+
+fn call(
+    storage: &dyn Storage,
+    clients: &mut HashMap<u64, Client>,
+    client: u64,
+    method: &str,
+    params: Value,
+) -> Result<(Value, Vec<String>), Failure> {
+    match method {
+        "lists" => Ok((json!(storage.names()?), Vec::new())),
+        "get" => {
+            let list = storage.load(&parse::<ListParams>(params)?.list)?;
+            Ok((to_value(&list), Vec::new()))
+        }
+        "create" => {
+            let params: ChangeParams = parse(params)?;
+            let changes: TodoChanges = parse(params.changes)?;
+            let mut list = storage.load(&params.list)?;
+            let index = list.add(changes)?;
+            storage.save(&list)?;
+            Ok((to_value(&list.todos[index]), vec![list.name]))
+        }
+        "update" => {
+            let params: ChangeParams = parse(params)?;
+            let id = match params.id {
+                Some(id) => id,
+                None => return Err(Failure(INVALID_PARAMS, String::from("Missing field `id`"))),
+            };
+            let changes: TodoChanges = parse(params.changes)?;
+            change(storage, &params.list, id, |list, index| {
+                list.todos[index].apply(changes)?;
+                Ok(Some(index))
+            })
+        }
+        "toggle" => {
+            let params: TodoParams = parse(params)?;
+            change(storage, &params.list, params.id, |list, index| {
+                list.todos[index].toggle();
+                Ok(Some(index))
+            })
+        }
+        "delete" => {
+            let params: TodoParams = parse(params)?;
+            change(storage, &params.list, params.id, |list, index| {
+                list.todos.remove(index);
+                Ok(None)
+            })
+        }
+        "move" => {
+            let params: MoveParams = parse(params)?;
+            let (todo, target) =
+                store::move_todo(storage, &params.list, params.id, &params.target)?;
+            let source = storage.load(&params.list)?.name;
+            let result = json!({ "todo": to_value(&todo), "list": target.name });
+            Ok((result, vec![source, target.name]))
+        }
+        "save" => {
+            let mut list: TodoList = match params.get("list").cloned() {
+                Some(list) => parse(list)?,
+                None => {
+                    return Err(Failure(
+                        INVALID_PARAMS,
+                        String::from("Missing field `list`"),
+                    ))
+                }
+            };
+            // The list is kept in the format it is stored in
+            if let Ok(existing) = storage.load(&list.name) {
+                list.format = existing.format;
+            }
+            storage.save(&list)?;
+            Ok((Value::Null, vec![list.name]))
+        }
+        "subscribe" => {
+            let params: SubscribeParams = match params {
+                Value::Null => SubscribeParams::default(),
+                params => parse(params)?,
+            };
+            if let Some(client) = clients.get_mut(&client) {
+                client.subscription = Some(params.lists.unwrap_or_default().into_iter().collect());
+            }
+            Ok((Value::Bool(true), Vec::new()))
+        }
+        "unsubscribe" => {
+            if let Some(client) = clients.get_mut(&client) {
+                client.subscription = None;
+            }
+            Ok((Value::Bool(true), Vec::new()))
+        }
+        _ => Err(Failure(
+            METHOD_NOT_FOUND,
+            format!("Unknown method '{}'", method),
+        )),
+    }
+}
+
+/// Loads a list, changes the todo with the given id and saves the list. The
+/// change returns the index of the todo to reply with, if any.
+fn change(
+    storage: &dyn Storage,
+    name: &str,
+    id: i16,
+    f: impl FnOnce(&mut TodoList, usize) -> Result<Option<usize>, Failure>,
+) -> Result<(Value, Vec<String>), Failure> {
+    let mut list = storage.load(name)?;
+    let index = match list.todos.iter().position(|todo| todo.id == id) {
+        Some(index) => index,
+        None => {
+            return Err(Error::TodoNotFound {
+                list: list.name,
+                id,
+            }
+            .into())
+        }
+    };
+
+    let result = match f(&mut list, index)? {
+        Some(index) => to_value(&list.todos[index]),
+        None => Value::Null,
+    };
+    storage.save(&list)?;
+    Ok((result, vec![list.name]))
+}
+
+/// Sends `changed` to the clients subscribed to a list.
+fn notify(clients: &HashMap<u64, Client>, list: &str) {
+    let notification = json!({
+        "jsonrpc": "2.0",
+        "method": "changed",
+        "params": { "list": list },
+    });
+    for client in clients.values() {
+        let subscribed = match &client.subscription {
+            Some(lists) => lists.is_empty() || lists.contains(list),
+            None => false,
+        };
+        if subscribed {
+            client.send(notification.clone());
+        }
+    }
+}
+
+fn parse<T: serde::de::DeserializeOwned>(params: Value) -> Result<T, Failure> {
+    match serde_json::from_value(params) {
+        Ok(params) => Ok(params),
+        Err(err) => Err(Failure(INVALID_PARAMS, err.to_string())),
+    }
+}
+
+fn to_value(value: &impl serde::Serialize) -> Value {
+    serde_json::to_value(value).expect("Error serializing json")
+}
+
+fn error(id: Value, code: i64, message: impl ToString) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": code, "message": message.to_string() },
+    })
+}
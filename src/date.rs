@@ -0,0 +1,171 @@
+//! Timestamps, stored as unix seconds and shown as UTC dates.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const SECONDS_PER_DAY: u64 = 86_400;
+
+/// The time `now` returns while the clock is stopped, 0 while it runs.
+static FROZEN: AtomicU64 = AtomicU64::new(0);
+
+pub fn now() -> u64 {
+    match FROZEN.load(Ordering::Relaxed) {
+        0 => SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0),
+        frozen => frozen,
+    }
+}
+
+/// Stops the clock at a time, so todos created or changed afterwards get
+/// the same timestamps on every run.
+pub fn freeze(timestamp: u64) {
+    FROZEN.store(timestamp, Ordering::Relaxed);
+}
+
+/// Formats a unix timestamp as a `YYYY-MM-DD` date (UTC).
+pub fn format_date(timestamp: u64) -> String {
+    let (year, month, day) = civil_from_days((timestamp / SECONDS_PER_DAY) as i64);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// Formats a unix timestamp as an ISO 8601 `YYYY-MM-DDTHH:MM:SSZ` datetime.
+pub fn format_datetime(timestamp: u64) -> String {
+    let seconds = timestamp % SECONDS_PER_DAY;
+    format!(
+        "{}T{:02}:{:02}:{:02}Z",
+        format_date(timestamp),
+        seconds / 3600,
+        seconds % 3600 / 60,
+        seconds % 60
+    )
+}
+
+/// Formats the time of a unix timestamp as `HH:MM` (UTC).
+pub fn format_time(timestamp: u64) -> String {
+    let seconds = timestamp % SECONDS_PER_DAY;
+    format!("{:02}:{:02}", seconds / 3600, seconds % 3600 / 60)
+}
+
+/// Formats a number of seconds as a short duration such as `3d 4h` or `12m`.
+pub fn format_duration(seconds: u64) -> String {
+    let days = seconds / SECONDS_PER_DAY;
+    let hours = seconds % SECONDS_PER_DAY / 3600;
+    let minutes = seconds % 3600 / 60;
+
+    if days > 0 {
+        format!("{}d {}h", days, hours)
+    } else if hours > 0 {
+        format!("{}h {}m", hours, minutes)
+    } else if minutes > 0 {
+        format!("{}m", minutes)
+    } else {
+        String::from("<1m")
+    }
+}
+
+/// Parses a `YYYY-MM-DD` date into the unix timestamp of its midnight (UTC).
+pub fn parse_date(text: &str) -> Option<u64> {
+    let mut parts = text.split('-');
+    let year: i64 = parse_digits(parts.next()?, 4)?;
+    let month: u32 = parse_digits(parts.next()?, 2)?;
+    let day: u32 = parse_digits(parts.next()?, 2)?;
+
+    if parts.next().is_some() || !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+
+    let days = days_from_civil(year, month, day);
+    if days < 0 {
+        return None;
+    }
+
+    Some(days as u64 * SECONDS_PER_DAY)
+}
+
+/// Formats a unix timestamp as an iCalendar `YYYYMMDDTHHMMSSZ` datetime.
+pub fn format_ical(timestamp: u64) -> String {
+    format_datetime(timestamp).replace(['-', ':'], "")
+}
+
+/// Parses an iCalendar `YYYYMMDD` date or `YYYYMMDDTHHMMSS[Z]` datetime.
+/// Floating and zoned times are treated as UTC.
+pub fn parse_ical(text: &str) -> Option<u64> {
+    let text = text.trim_end_matches('Z');
+    let (day, time) = text.split_once('T').unwrap_or((text, "000000"));
+
+    if day.len() != 8 || !day.is_ascii() {
+        return None;
+    }
+    let date = parse_date(format!("{}-{}-{}", &day[..4], &day[4..6], &day[6..]).as_str())?;
+
+    let hours: u64 = parse_digits(time.get(..2)?, 2)?;
+    let minutes: u64 = parse_digits(time.get(2..4)?, 2)?;
+    let seconds: u64 = parse_digits(time.get(4..)?, 2)?;
+    if hours > 23 || minutes > 59 || seconds > 60 {
+        return None;
+    }
+
+    Some(date + hours * 3600 + minutes * 60 + seconds)
+}
+
+fn parse_digits<T: std::str::FromStr>(text: &str, len: usize) -> Option<T> {
+    if text.len() != len || !text.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    text.parse().ok()
+}
+
+// Conversions between days since the unix epoch and proleptic gregorian
+// dates, see http://howardhinnant.github.io/date_algorithms.html
+
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    (year, month, day)
+}
+
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let mp = i64::from((month + 9) % 12);
+    let doy = (153 * mp + 2) / 5 + i64::from(day) - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+
+    era * 146_097 + doe - 719_468
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_ical_dates_and_datetimes() {
+        assert_eq!(parse_ical("20240102"), parse_date("2024-01-02"));
+        assert_eq!(
+            parse_ical("20240102T030405Z"),
+            parse_date("2024-01-02").map(|day| day + 3 * 3600 + 4 * 60 + 5)
+        );
+        assert_eq!(parse_ical(&format_ical(1_704_164_645)), Some(1_704_164_645));
+    }
+
+    #[test]
+    fn rejects_invalid_ical_dates() {
+        assert_eq!(parse_ical("202é010"), None);
+        assert_eq!(parse_ical("2024010é"), None);
+        assert_eq!(parse_ical("20240102T03é05"), None);
+        assert_eq!(parse_ical("2024-1-2"), None);
+        assert_eq!(parse_ical("20241302"), None);
+        assert_eq!(parse_ical("20240102T250000"), None);
+    }
+}
@@ -1,7 +1,7 @@
 use std::fs::OpenOptions;
 use std::io::Write;
 
-#[allow(unused)]
+#[allow(unused, clippy::single_match)]
 pub fn debug_log(message: &str) {
     match OpenOptions::new().write(true).open("/dev/pts/1") {
         Ok(mut file) => {
@@ -1,6 +1,7 @@
 use termion::clear;
 use termion::color;
 use termion::cursor;
+use termion::screen;
 use termion::style;
 
 pub fn warning(text: String) -> String {
@@ -19,6 +20,86 @@ pub fn bold(text: String) -> String {
     format!("{}{}{}", style::Bold, text, style::Reset)
 }
 
+/// Renders values as a one line chart of block characters.
+pub fn sparkline(values: &[usize]) -> String {
+    const BLOCKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+    let max = values.iter().copied().max().unwrap_or(0);
+
+    values
+        .iter()
+        .map(|v| match max {
+            0 => BLOCKS[0],
+            _ => BLOCKS[v * (BLOCKS.len() - 1) / max],
+        })
+        .collect()
+}
+
+/// Renders a horizontal bar of at most `width` cells, scaled against `max`.
+pub fn bar(value: usize, max: usize, width: u16) -> String {
+    let cells = match max {
+        0 => 0,
+        _ => value * width as usize / max,
+    };
+    "█".repeat(cells)
+}
+
+/// Aligns rows into columns separated by two spaces. Columns flagged in
+/// `right` are right aligned.
+pub fn table(rows: &[Vec<String>], right: &[bool]) -> String {
+    let mut widths: Vec<u16> = Vec::new();
+    for row in rows {
+        for (i, cell) in row.iter().enumerate() {
+            let len = visible_length(cell);
+            match widths.get_mut(i) {
+                Some(width) => *width = (*width).max(len),
+                None => widths.push(len),
+            }
+        }
+    }
+
+    let mut out = String::new();
+    for row in rows {
+        let mut line = String::new();
+        for (i, cell) in row.iter().enumerate() {
+            let padding = " ".repeat((widths[i] - visible_length(cell)) as usize);
+            if i > 0 {
+                line.push_str("  ");
+            }
+            if right.get(i).copied().unwrap_or(false) {
+                line.push_str(&padding);
+                line.push_str(cell);
+            } else {
+                line.push_str(cell);
+                line.push_str(&padding);
+            }
+        }
+        out.push_str(line.trim_end());
+        out.push('\n');
+    }
+    out
+}
+
+/// A full width inverted line with `left` and `right` at its edges.
+pub fn status_bar(left: String, right: String, width: u16) -> String {
+    let used = visible_length(&left) + visible_length(&right);
+    let line = if used + 2 <= width {
+        format!(
+            " {}{}{} ",
+            left,
+            " ".repeat((width - used - 2) as usize),
+            right
+        )
+    } else {
+        let line: String = format!(" {}", left).chars().take(width as usize).collect();
+        format!("{:<1$}", line, width as usize)
+    };
+    invert(line)
+}
+
+pub fn invert(text: String) -> String {
+    format!("{}{}{}", style::Invert, text, style::Reset)
+}
+
 pub fn input(name: &str, x: u16, y: u16, width: u16) -> String {
     let mut out = String::new();
 
@@ -66,6 +147,7 @@ pub fn bordered(content: String, title: String, title_bottom: String, width: u16
     out
 }
 
+#[allow(clippy::explicit_counter_loop)]
 pub fn position(content: String, x: u16, y: u16) -> String {
     let mut out = String::new();
     let mut counter: u16 = 0;
@@ -77,6 +159,7 @@ pub fn position(content: String, x: u16, y: u16) -> String {
     out
 }
 
+#[allow(clippy::single_char_add_str)]
 pub fn title_border_top(mut length: u16, title: String) -> String {
     length = length - (title.len() as u16) - 2;
     let mut bar = String::new();
@@ -90,6 +173,7 @@ pub fn title_border_top(mut length: u16, title: String) -> String {
     format!("╭{}╮\r\n", bar)
 }
 
+#[allow(clippy::single_char_add_str)]
 pub fn title_border_bottom(mut length: u16, title: String) -> String {
     length = length - (title.len() as u16) - 2;
     let mut bar = String::new();
@@ -143,6 +227,23 @@ pub fn show_cursor() -> String {
     format!("{}", cursor::Show)
 }
 
+pub fn alternate_screen() -> String {
+    format!("{}", screen::ToAlternateScreen)
+}
+
+pub fn main_screen() -> String {
+    format!("{}", screen::ToMainScreen)
+}
+
+pub fn enable_mouse() -> String {
+    String::from("\x1B[?1000h\x1B[?1002h\x1B[?1015h\x1B[?1006h")
+}
+
+pub fn disable_mouse() -> String {
+    String::from("\x1B[?1006l\x1B[?1015l\x1B[?1002l\x1B[?1000l")
+}
+
+#[allow(clippy::while_let_on_iterator)]
 pub fn visible_length(input: &str) -> u16 {
     let mut count = 0;
     let mut in_escape = false;
@@ -0,0 +1,129 @@
+//! The error type of every fallible operation of the library.
+
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug)]
+pub enum Error {
+    /// A file or folder couldn't be read, written, created, moved or removed.
+    Io {
+        operation: &'static str,
+        path: PathBuf,
+        source: io::Error,
+    },
+    /// A file holds something that isn't a todo list, archive or setting.
+    Parse {
+        path: PathBuf,
+        message: String,
+    },
+    /// There is no list with the given name in the todo path.
+    ListNotFound(String),
+    ListExists(String),
+    /// A partial list name matches more than one list.
+    AmbiguousList {
+        input: String,
+        matches: Vec<String>,
+    },
+    TodoNotFound {
+        list: String,
+        id: i16,
+    },
+    /// Todos can't be moved into the list they are already in.
+    SameList(String),
+    /// A name can't be used for a list, see `lists::validate`, or a file
+    /// name doesn't contain one.
+    InvalidName(String),
+    /// Changes sent for a todo can't be applied, see `TodoChanges`.
+    InvalidTodo(String),
+    UnknownFormat(String),
+    /// Settings that can't be used together.
+    InvalidSettings(String),
+    UnknownColumn(String),
+    NoHomeDir,
+    /// A query of the sqlite backend failed.
+    Database(rusqlite::Error),
+    /// A git command failed, with what it printed to stderr.
+    Git {
+        args: String,
+        message: String,
+    },
+}
+
+impl Error {
+    pub fn io(operation: &'static str, path: impl Into<PathBuf>, source: io::Error) -> Self {
+        Error::Io {
+            operation,
+            path: path.into(),
+            source,
+        }
+    }
+
+    pub fn parse(path: impl Into<PathBuf>, message: impl fmt::Display) -> Self {
+        Error::Parse {
+            path: path.into(),
+            message: message.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io {
+                operation,
+                path,
+                source,
+            } => write!(
+                f,
+                "Unable to {} '{}': {}",
+                operation,
+                path.display(),
+                source
+            ),
+            Error::Parse { path, message } => {
+                write!(f, "Unable to parse '{}': {}", path.display(), message)
+            }
+            Error::ListNotFound(name) => write!(f, "No todo list named '{}'", name),
+            Error::ListExists(name) => write!(f, "A todo list named '{}' already exists", name),
+            Error::AmbiguousList { input, matches } => write!(
+                f,
+                "'{}' matches several lists: {}",
+                input,
+                matches.join(", ")
+            ),
+            Error::TodoNotFound { list, id } => write!(f, "No todo with id {} in '{}'", id, list),
+            Error::SameList(name) => write!(f, "Todos are already in '{}'", name),
+            Error::InvalidName(name) => write!(f, "'{}' is not a valid list name", name),
+            Error::InvalidTodo(message) => write!(f, "Invalid todo: {}", message),
+            Error::UnknownFormat(name) => write!(
+                f,
+                "Unknown export format '{}' (expected txt, csv, jsonl or ics)",
+                name
+            ),
+            Error::UnknownColumn(name) => write!(f, "Unknown column '{}'", name),
+            Error::InvalidSettings(message) => write!(f, "Invalid settings: {}", message),
+            Error::NoHomeDir => write!(f, "Unable to load home path"),
+            Error::Database(err) => write!(f, "Database error: {}", err),
+            Error::Git { args, message } => write!(f, "'git {}' failed: {}", args, message),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io { source, .. } => Some(source),
+            Error::Database(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<rusqlite::Error> for Error {
+    fn from(err: rusqlite::Error) -> Self {
+        Error::Database(err)
+    }
+}
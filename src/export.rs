@@ -0,0 +1,248 @@
+//! Tabular exports of todo lists for spreadsheets and data pipelines.
+
+use crate::date;
+use crate::error::{Error, Result};
+use crate::ical;
+use crate::todo::{Todo, TodoList};
+use crate::todotxt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    TodoTxt,
+    Csv,
+    JsonLines,
+    ICalendar,
+}
+
+impl ExportFormat {
+    pub fn parse(name: &str) -> Result<Self> {
+        match name {
+            "txt" | "todotxt" => Ok(ExportFormat::TodoTxt),
+            "csv" => Ok(ExportFormat::Csv),
+            "jsonl" => Ok(ExportFormat::JsonLines),
+            "ics" | "ical" => Ok(ExportFormat::ICalendar),
+            other => Err(Error::UnknownFormat(other.to_string())),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Column {
+    List,
+    Id,
+    Text,
+    Done,
+    Priority,
+    Tags,
+    Created,
+    Modified,
+    Completed,
+    Due,
+}
+
+impl Column {
+    pub const ALL: [Column; 10] = [
+        Column::List,
+        Column::Id,
+        Column::Text,
+        Column::Done,
+        Column::Priority,
+        Column::Tags,
+        Column::Created,
+        Column::Modified,
+        Column::Completed,
+        Column::Due,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Column::List => "list",
+            Column::Id => "id",
+            Column::Text => "text",
+            Column::Done => "done",
+            Column::Priority => "priority",
+            Column::Tags => "tags",
+            Column::Created => "created",
+            Column::Modified => "modified",
+            Column::Completed => "completed",
+            Column::Due => "due",
+        }
+    }
+
+    /// Parses a comma separated list of column names.
+    pub fn parse_list(names: &str) -> Result<Vec<Column>> {
+        names
+            .split(',')
+            .map(|name| {
+                Column::ALL
+                    .into_iter()
+                    .find(|c| c.name() == name.trim())
+                    .ok_or_else(|| Error::UnknownColumn(name.trim().to_string()))
+            })
+            .collect()
+    }
+
+    fn text(&self, list: &TodoList, todo: &Todo) -> String {
+        match self {
+            Column::List => list.name.clone(),
+            Column::Id => todo.id.to_string(),
+            Column::Text => todo.item.clone(),
+            Column::Done => todo.done.to_string(),
+            Column::Priority => todo.priority.to_string(),
+            Column::Tags => todo.tags.join(";"),
+            Column::Created => todo
+                .created_at
+                .map(date::format_datetime)
+                .unwrap_or_default(),
+            Column::Modified => todo
+                .modified_at
+                .map(date::format_datetime)
+                .unwrap_or_default(),
+            Column::Completed => todo
+                .completed_at
+                .map(date::format_datetime)
+                .unwrap_or_default(),
+            Column::Due => todo.due_at.map(date::format_datetime).unwrap_or_default(),
+        }
+    }
+
+    fn json(&self, list: &TodoList, todo: &Todo) -> serde_json::Value {
+        match self {
+            Column::Id => todo.id.into(),
+            Column::Done => todo.done.into(),
+            Column::Priority => todo.priority.into(),
+            Column::Tags => todo.tags.clone().into(),
+            Column::Created => todo.created_at.map(date::format_datetime).into(),
+            Column::Modified => todo.modified_at.map(date::format_datetime).into(),
+            Column::Completed => todo.completed_at.map(date::format_datetime).into(),
+            Column::Due => todo.due_at.map(date::format_datetime).into(),
+            Column::List | Column::Text => self.text(list, todo).into(),
+        }
+    }
+}
+
+pub fn export(lists: &[TodoList], format: ExportFormat, columns: &[Column]) -> String {
+    match format {
+        ExportFormat::TodoTxt => lists.iter().map(todotxt::serialize).collect(),
+        ExportFormat::Csv => csv(lists, columns),
+        ExportFormat::JsonLines => json_lines(lists, columns),
+        ExportFormat::ICalendar => ical::serialize(lists),
+    }
+}
+
+pub fn csv(lists: &[TodoList], columns: &[Column]) -> String {
+    let header: Vec<String> = columns.iter().map(|c| csv_field(c.name())).collect();
+    let mut out = header.join(",");
+    out.push_str("\r\n");
+
+    for list in lists {
+        for todo in list.todos.iter() {
+            let row: Vec<String> = columns
+                .iter()
+                .map(|c| csv_field(c.text(list, todo).as_str()))
+                .collect();
+            out.push_str(row.join(",").as_str());
+            out.push_str("\r\n");
+        }
+    }
+
+    out
+}
+
+pub fn json_lines(lists: &[TodoList], columns: &[Column]) -> String {
+    let mut out = String::new();
+
+    for list in lists {
+        for todo in list.todos.iter() {
+            // Built by hand so the keys keep the requested column order
+            let fields: Vec<String> = columns
+                .iter()
+                .map(|c| {
+                    format!(
+                        "{}:{}",
+                        serde_json::Value::from(c.name()),
+                        c.json(list, todo)
+                    )
+                })
+                .collect();
+            out.push('{');
+            out.push_str(fields.join(",").as_str());
+            out.push_str("}\n");
+        }
+    }
+
+    out
+}
+
+/// Quotes a field as described in RFC 4180 when it needs to be.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn list() -> TodoList {
+        let mut list = TodoList::new(String::from("home"));
+        list.todos.push(Todo {
+            id: 1,
+            item: String::from("say \"hi\", then leave"),
+            tags: vec![String::from("+a"), String::from("@b")],
+            done: true,
+            due_at: date::parse_date("2024-01-02"),
+            ..Default::default()
+        });
+        list.todos.push(Todo {
+            id: 2,
+            item: String::from("two\nlines"),
+            ..Default::default()
+        });
+        list
+    }
+
+    #[test]
+    fn quotes_csv_fields_only_when_needed() {
+        assert_eq!(csv_field("plain"), "plain");
+        assert_eq!(csv_field(""), "");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(csv_field("a\r\nb"), "\"a\r\nb\"");
+    }
+
+    #[test]
+    fn writes_csv_rows_in_column_order() {
+        let columns = Column::parse_list("id, text,tags,due").unwrap();
+        assert_eq!(
+            csv(&[list()], &columns),
+            "id,text,tags,due\r\n\
+             1,\"say \"\"hi\"\", then leave\",+a;@b,2024-01-02T00:00:00Z\r\n\
+             2,\"two\nlines\",,\r\n"
+        );
+    }
+
+    #[test]
+    fn writes_json_lines_with_typed_values() {
+        let columns = Column::parse_list("list,id,done,tags,completed").unwrap();
+        let out = json_lines(&[list()], &columns);
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(
+            lines[0],
+            r#"{"list":"home","id":1,"done":true,"tags":["+a","@b"],"completed":null}"#
+        );
+        assert_eq!(lines.len(), 2);
+    }
+
+    #[test]
+    fn rejects_unknown_columns_and_formats() {
+        assert!(matches!(
+            Column::parse_list("id,nope"),
+            Err(Error::UnknownColumn(name)) if name == "nope"
+        ));
+        assert!(ExportFormat::parse("xml").is_err());
+    }
+}
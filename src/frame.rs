@@ -0,0 +1,419 @@
+//! The screen as a grid of cells. What the panel draws is interpreted into a
+//! `Frame`, and the `Renderer` writes only the cells that changed since the
+//! last frame, so redraws don't flicker and output doesn't grow.
+
+use std::mem;
+use termion::{clear, cursor, style};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+#[derive(Clone, PartialEq, Eq)]
+struct Cell {
+    /// A character with the zero width characters that follow it, empty for
+    /// the second cell of a wide character.
+    symbol: String,
+    /// Escape sequences of the style the symbol is drawn in, empty for the
+    /// default style.
+    style: String,
+}
+
+impl Cell {
+    fn continuation(style: String) -> Self {
+        Cell {
+            symbol: String::new(),
+            style,
+        }
+    }
+
+    fn is_continuation(&self) -> bool {
+        self.symbol.is_empty()
+    }
+
+    /// How many cells the symbol covers on the terminal.
+    fn width(&self) -> usize {
+        self.symbol.width()
+    }
+}
+
+impl Default for Cell {
+    fn default() -> Self {
+        Cell {
+            symbol: String::from(" "),
+            style: String::new(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Frame {
+    width: u16,
+    height: u16,
+    cells: Vec<Cell>,
+    /// Where the next symbol is drawn, and the cursor is shown, from 0.
+    x: u16,
+    y: u16,
+    /// Style of the next symbols.
+    style: String,
+    cursor_visible: bool,
+}
+
+impl Frame {
+    pub fn new(width: u16, height: u16) -> Self {
+        Frame {
+            width,
+            height,
+            cells: vec![Cell::default(); width as usize * height as usize],
+            x: 0,
+            y: 0,
+            style: String::new(),
+            cursor_visible: true,
+        }
+    }
+
+    pub fn size(&self) -> (u16, u16) {
+        (self.width, self.height)
+    }
+
+    /// Interprets text with the escape sequences of `draw` like a terminal
+    /// would. Returns the sequences that don't change cells, e.g. mouse
+    /// reporting, to be written as they are.
+    pub fn draw(&mut self, output: &str) -> String {
+        let mut passthrough = String::new();
+        let mut chars = output.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '\x1B' if chars.peek() == Some(&'[') => {
+                    chars.next();
+                    let mut params = String::new();
+                    for c in chars.by_ref() {
+                        if ('\x40'..='\x7E').contains(&c) {
+                            self.control(&params, c, &mut passthrough);
+                            break;
+                        }
+                        params.push(c);
+                    }
+                }
+                '\x1B' => {
+                    passthrough.push(c);
+                    passthrough.extend(chars.next());
+                }
+                '\r' => self.x = 0,
+                '\n' => self.y = (self.y + 1).min(self.height.saturating_sub(1)),
+                c if c.is_control() => {}
+                c => self.put(c),
+            }
+        }
+        passthrough
+    }
+
+    /// The symbols of every row, without styles or trailing spaces.
+    pub fn text(&self) -> String {
+        let mut out = String::new();
+        for row in self.cells.chunks(self.width.max(1) as usize) {
+            let line: String = row.iter().map(|cell| cell.symbol.as_str()).collect();
+            out.push_str(line.trim_end());
+            out.push('\n');
+        }
+        out
+    }
+
+    fn put(&mut self, symbol: char) {
+        let width = symbol.width().unwrap_or(0) as u16;
+        if width == 0 {
+            // Combines with the character before it, as a terminal does
+            if self.x > 0 && self.x <= self.width && self.y < self.height {
+                let mut index = self.index(self.x - 1, self.y);
+                if self.cells[index].is_continuation() && self.x > 1 {
+                    index -= 1;
+                }
+                self.cells[index].symbol.push(symbol);
+            }
+            return;
+        }
+
+        if self.x < self.width && self.y < self.height {
+            let index = self.index(self.x, self.y);
+            let style = self.style.clone();
+            if width == 1 {
+                self.set(
+                    index,
+                    Cell {
+                        symbol: symbol.to_string(),
+                        style,
+                    },
+                );
+            } else if self.x + 1 < self.width {
+                self.set(
+                    index,
+                    Cell {
+                        symbol: symbol.to_string(),
+                        style: style.clone(),
+                    },
+                );
+                self.set(index + 1, Cell::continuation(style));
+            } else {
+                // Half of it would be cut off
+                self.set(
+                    index,
+                    Cell {
+                        symbol: String::from(" "),
+                        style,
+                    },
+                );
+            }
+        }
+        self.x = self.x.saturating_add(width);
+    }
+
+    /// Replaces a cell, blanking what is left of a wide character it
+    /// overwrites half of.
+    fn set(&mut self, index: usize, cell: Cell) {
+        let x = index % self.width as usize;
+        if self.cells[index].is_continuation() && x > 0 && !cell.is_continuation() {
+            self.cells[index - 1] = Cell::default();
+        }
+        if self.cells[index].width() > 1 && x + 1 < self.width as usize {
+            self.cells[index + 1] = Cell::default();
+        }
+        self.cells[index] = cell;
+    }
+
+    fn control(&mut self, params: &str, command: char, passthrough: &mut String) {
+        let end = self.cells.len();
+        let at = self.index(self.x.min(self.width.saturating_sub(1)), self.y);
+        let line = self.index(0, self.y);
+        match (command, params) {
+            ('H' | 'f', _) => {
+                let mut numbers = params.split(';').map(|n| n.parse::<u16>().unwrap_or(1));
+                let y = numbers.next().unwrap_or(1).max(1) - 1;
+                let x = numbers.next().unwrap_or(1).max(1) - 1;
+                self.y = y.min(self.height.saturating_sub(1));
+                self.x = x.min(self.width.saturating_sub(1));
+            }
+            ('J', "" | "0") => self.erase(at, end),
+            ('J', "1") => self.erase(0, at + 1),
+            ('J', "2") => self.erase(0, end),
+            ('K', "" | "0") => self.erase(at, line + self.width as usize),
+            ('K', "1") => self.erase(line, at + 1),
+            ('K', "2") => self.erase(line, line + self.width as usize),
+            ('m', "" | "0") => self.style.clear(),
+            ('m', _) => self.style.push_str(&format!("\x1B[{}m", params)),
+            ('h', "?25") => self.cursor_visible = true,
+            ('l', "?25") => self.cursor_visible = false,
+            _ => passthrough.push_str(&format!("\x1B[{}{}", params, command)),
+        }
+    }
+
+    fn erase(&mut self, from: usize, to: usize) {
+        let to = to.min(self.cells.len());
+        if from < to {
+            self.set(from, Cell::default());
+            self.set(to - 1, Cell::default());
+            self.cells[from..to].fill(Cell::default());
+        }
+    }
+
+    fn index(&self, x: u16, y: u16) -> usize {
+        y.min(self.height.saturating_sub(1)) as usize * self.width as usize + x as usize
+    }
+
+    /// The same frame in another size, keeping the cells that still fit.
+    fn resized(&self, width: u16, height: u16) -> Frame {
+        let mut frame = Frame::new(width, height);
+        for y in 0..height.min(self.height) {
+            for x in 0..width.min(self.width) {
+                let index = frame.index(x, y);
+                frame.cells[index] = self.cells[self.index(x, y)].clone();
+            }
+            // A wide character in the last column lost its second half
+            if width > 0 && width < self.width && y < self.height {
+                let last = frame.index(width - 1, y);
+                if frame.cells[last].width() > 1 {
+                    frame.cells[last] = Cell::default();
+                }
+            }
+        }
+        frame.x = self.x.min(width.saturating_sub(1));
+        frame.y = self.y.min(height.saturating_sub(1));
+        frame.style = self.style.clone();
+        frame.cursor_visible = self.cursor_visible;
+        frame
+    }
+}
+
+/// Writes frames to the terminal, emitting only what changed since the
+/// last one.
+pub struct Renderer {
+    frame: Frame,
+    /// The frame as it is on the terminal, `None` when it has to be painted
+    /// completely.
+    shown: Option<Frame>,
+    passthrough: String,
+}
+
+impl Renderer {
+    pub fn new(width: u16, height: u16) -> Self {
+        Renderer {
+            frame: Frame::new(width, height),
+            shown: None,
+            passthrough: String::new(),
+        }
+    }
+
+    pub fn frame(&self) -> &Frame {
+        &self.frame
+    }
+
+    pub fn draw(&mut self, output: &str) {
+        let passthrough = self.frame.draw(output);
+        self.passthrough.push_str(&passthrough);
+    }
+
+    /// Paints everything on the next flush, e.g. after the terminal showed
+    /// something else.
+    pub fn invalidate(&mut self) {
+        self.shown = None;
+    }
+
+    pub fn resize(&mut self, width: u16, height: u16) {
+        if self.frame.size() != (width, height) {
+            self.frame = self.frame.resized(width, height);
+            self.invalidate();
+        }
+    }
+
+    /// What to write to the terminal to show the current frame.
+    pub fn flush(&mut self) -> String {
+        let mut out = mem::take(&mut self.passthrough);
+        let (width, height) = self.frame.size();
+        let blank;
+        let shown = match &self.shown {
+            Some(shown) => shown,
+            None => {
+                out.push_str(&format!("{}{}", style::Reset, clear::All));
+                blank = Frame {
+                    // The cursor is moved and shown again after painting
+                    cursor_visible: !self.frame.cursor_visible,
+                    ..Frame::new(width, height)
+                };
+                &blank
+            }
+        };
+
+        let mut cells = String::new();
+        let mut style: Option<&str> = None;
+        let mut next: Option<usize> = None;
+        for (index, cell) in self.frame.cells.iter().enumerate() {
+            // Drawn with the wide character before it, which changed too
+            if *cell == shown.cells[index] || cell.is_continuation() {
+                continue;
+            }
+            if next != Some(index) {
+                let (x, y) = (index % width as usize, index / width as usize);
+                cells.push_str(&cursor::Goto(x as u16 + 1, y as u16 + 1).to_string());
+            }
+            if style != Some(cell.style.as_str()) {
+                cells.push_str(&format!("{}{}", style::Reset, cell.style));
+                style = Some(&cell.style);
+            }
+            cells.push_str(&cell.symbol);
+            // The cursor doesn't move on to the next row by itself
+            next = Some(index + cell.width()).filter(|next| next % width as usize != 0);
+        }
+        if style.is_some() {
+            cells.push_str(style::Reset.as_ref());
+        }
+
+        let painted = !cells.is_empty();
+        if painted {
+            // Hidden while painting, so it doesn't jump around
+            out.push_str(cursor::Hide.as_ref());
+            out.push_str(&cells);
+        }
+        let frame = &self.frame;
+        if painted || (shown.x, shown.y) != (frame.x, frame.y) {
+            let x = frame.x.min(width.saturating_sub(1));
+            out.push_str(&cursor::Goto(x + 1, frame.y + 1).to_string());
+        }
+        if frame.cursor_visible && (painted || !shown.cursor_visible) {
+            out.push_str(cursor::Show.as_ref());
+        } else if !frame.cursor_visible && shown.cursor_visible && !painted {
+            out.push_str(cursor::Hide.as_ref());
+        }
+
+        self.shown = Some(self.frame.clone());
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(output: &str) -> Frame {
+        let mut frame = Frame::new(6, 2);
+        frame.draw(output);
+        frame
+    }
+
+    #[test]
+    fn draws_text_at_the_cursor() {
+        let frame = frame("ab\x1B[2;3Hcd\x1B[1;2H\x1B[K");
+        assert_eq!(frame.text(), "a\n  cd\n");
+        assert_eq!((frame.x, frame.y), (1, 0));
+    }
+
+    #[test]
+    fn keeps_sequences_that_dont_change_cells() {
+        let mut frame = Frame::new(6, 2);
+        assert_eq!(frame.draw("\x1B[?1000ha\x1B[?25l"), "\x1B[?1000h");
+        assert!(!frame.cursor_visible);
+    }
+
+    #[test]
+    fn wide_characters_take_two_cells() {
+        let frame = frame("a日b\r\n12345日");
+        assert_eq!(frame.text(), "a日b\n12345\n");
+        assert!(frame.cells[2].is_continuation());
+        assert_eq!(frame.cells[3].symbol, "b");
+    }
+
+    #[test]
+    fn zero_width_characters_combine() {
+        let frame = frame("e\u{301}日\u{301}x");
+        assert_eq!(frame.cells[0].symbol, "e\u{301}");
+        assert_eq!(frame.cells[1].symbol, "日\u{301}");
+        assert_eq!(frame.text(), "e\u{301}日\u{301}x\n\n");
+    }
+
+    #[test]
+    fn overwriting_half_of_a_wide_character_blanks_the_other_half() {
+        assert_eq!(frame("日本\x1B[1;2Hx").text(), " x本\n\n");
+        assert_eq!(frame("日本\x1B[1;3Hx").text(), "日x\n\n");
+        assert_eq!(frame("日本\x1B[1;2H\x1B[K").text(), "\n\n");
+    }
+
+    #[test]
+    fn flushes_only_changed_cells() {
+        let mut renderer = Renderer::new(6, 2);
+        renderer.draw("abc");
+        let first = renderer.flush();
+        assert!(first.contains(&clear::All.to_string()));
+
+        renderer.draw("\x1B[1;2HX");
+        let out = renderer.flush();
+        assert!(!out.contains(&clear::All.to_string()));
+        assert!(out.contains(&format!("{}{}X", cursor::Goto(2, 1), style::Reset)));
+        assert!(!out.contains('a') && !out.contains('c'));
+
+        assert_eq!(renderer.flush(), "");
+    }
+
+    #[test]
+    fn flush_moves_past_wide_characters() {
+        let mut renderer = Renderer::new(6, 2);
+        renderer.draw("日b");
+        let out = renderer.flush();
+        // b follows without moving the cursor, the second cell isn't written
+        assert!(out.contains("日b"));
+    }
+}
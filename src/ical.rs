@@ -0,0 +1,356 @@
+//! iCalendar (RFC 5545) export and import of todo lists as VTODO components.
+//!
+//! Items that weren't imported from a calendar get a UID when they are first
+//! exported, which is stored with them, so exporting a list and importing it
+//! again updates the existing items instead of duplicating them.
+
+use crate::date;
+use crate::todo::{Todo, TodoList};
+use std::process;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const MAX_LINE_LENGTH: usize = 75;
+
+/// Makes the generated UIDs unique within the process.
+static UID_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+pub fn serialize(lists: &[TodoList]) -> String {
+    let mut out = String::new();
+    let stamp = date::format_ical(date::now());
+
+    push_line(&mut out, "BEGIN:VCALENDAR");
+    push_line(&mut out, "VERSION:2.0");
+    push_line(&mut out, "PRODID:-//rustodo//rustodo//EN");
+
+    for list in lists {
+        for todo in list.todos.iter() {
+            push_line(&mut out, "BEGIN:VTODO");
+            if let Some(uid) = &todo.uid {
+                push_property(&mut out, "UID", &escape(uid));
+            }
+            push_property(&mut out, "DTSTAMP", &stamp);
+            push_property(&mut out, "SUMMARY", &escape(&todo.item));
+
+            if todo.done {
+                push_property(&mut out, "STATUS", "COMPLETED");
+            } else {
+                push_property(&mut out, "STATUS", "NEEDS-ACTION");
+            }
+            if todo.priority > 0 {
+                // iCalendar priorities only range from 1 (highest) to 9 (lowest)
+                push_property(&mut out, "PRIORITY", &todo.priority.min(9).to_string());
+            }
+            if !todo.tags.is_empty() {
+                let categories: Vec<String> = todo.tags.iter().map(|t| escape(t)).collect();
+                push_property(&mut out, "CATEGORIES", &categories.join(","));
+            }
+            if let Some(created_at) = todo.created_at {
+                push_property(&mut out, "CREATED", &date::format_ical(created_at));
+            }
+            if let Some(modified_at) = todo.modified_at {
+                push_property(&mut out, "LAST-MODIFIED", &date::format_ical(modified_at));
+            }
+            if let Some(due_at) = todo.due_at {
+                push_property(&mut out, "DUE", &date::format_ical(due_at));
+            }
+            if let Some(completed_at) = todo.completed_at {
+                push_property(&mut out, "COMPLETED", &date::format_ical(completed_at));
+            }
+
+            push_line(&mut out, "END:VTODO");
+        }
+    }
+
+    push_line(&mut out, "END:VCALENDAR");
+    out
+}
+
+/// Reads every VTODO of a calendar. The returned todos have no ids yet, see
+/// `merge` to add them to a list.
+pub fn parse(data: &str) -> Vec<Todo> {
+    let mut todos = Vec::new();
+    let mut current: Option<Todo> = None;
+
+    for line in unfold(data) {
+        let (name, value) = match split_property(&line) {
+            Some(property) => property,
+            None => continue,
+        };
+
+        match (name.as_str(), current.as_mut()) {
+            ("BEGIN", None) if value == "VTODO" => current = Some(Todo::default()),
+            ("END", Some(_)) if value == "VTODO" => todos.extend(current.take()),
+            ("UID", Some(todo)) => todo.uid = Some(unescape(value)),
+            ("SUMMARY", Some(todo)) => todo.item = unescape(value),
+            ("STATUS", Some(todo)) => todo.done = value == "COMPLETED",
+            ("PRIORITY", Some(todo)) => todo.priority = value.parse().unwrap_or(0),
+            ("CATEGORIES", Some(todo)) => {
+                for category in split_list(value) {
+                    if !category.is_empty() && !todo.tags.contains(&category) {
+                        todo.tags.push(category);
+                    }
+                }
+            }
+            ("CREATED", Some(todo)) => todo.created_at = date::parse_ical(value),
+            ("LAST-MODIFIED", Some(todo)) => todo.modified_at = date::parse_ical(value),
+            ("DUE", Some(todo)) => todo.due_at = date::parse_ical(value),
+            ("COMPLETED", Some(todo)) => {
+                todo.completed_at = date::parse_ical(value);
+                todo.done = true;
+            }
+            _ => {}
+        }
+    }
+
+    todos
+}
+
+/// Adds imported todos to a list, replacing the items with the same UID.
+pub fn merge(list: &mut TodoList, todos: Vec<Todo>) {
+    for mut todo in todos {
+        let existing = todo.uid.as_ref().and_then(|imported| {
+            list.todos
+                .iter()
+                .position(|t| t.uid.as_ref() == Some(imported))
+        });
+
+        match existing {
+            Some(index) => {
+                todo.id = list.todos[index].id;
+                list.todos[index] = todo;
+            }
+            None => {
+                todo.id = list.next_id();
+                list.todos.push(todo);
+            }
+        }
+    }
+}
+
+/// Gives the todos without a UID a new one, which has to be saved with
+/// them before they are exported. Returns whether any todo got one.
+pub fn assign_uids(list: &mut TodoList) -> bool {
+    let mut assigned = false;
+    for todo in list.todos.iter_mut().filter(|todo| todo.uid.is_none()) {
+        todo.uid = Some(new_uid());
+        assigned = true;
+    }
+    assigned
+}
+
+fn new_uid() -> String {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.as_nanos())
+        .unwrap_or_default();
+    format!(
+        "{:x}-{:x}-{:x}@rustodo",
+        nanos,
+        process::id(),
+        UID_COUNTER.fetch_add(1, Ordering::Relaxed)
+    )
+}
+
+fn push_property(out: &mut String, name: &str, value: &str) {
+    push_line(out, format!("{}:{}", name, value).as_str());
+}
+
+/// Writes a content line, folding it after 75 octets as the RFC requires.
+fn push_line(out: &mut String, line: &str) {
+    let mut length = 0;
+    for c in line.chars() {
+        if length + c.len_utf8() > MAX_LINE_LENGTH {
+            out.push_str("\r\n ");
+            length = 1;
+        }
+        out.push(c);
+        length += c.len_utf8();
+    }
+    out.push_str("\r\n");
+}
+
+fn unfold(data: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for line in data.lines() {
+        let line = line.trim_end_matches('\r');
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(continuation), Some(last)) => last.push_str(continuation),
+            _ => lines.push(line.to_string()),
+        }
+    }
+    lines
+}
+
+/// Splits `NAME;PARAM=VALUE:value` into the upper case name and the raw value.
+/// Parameters such as `TZID` or `VALUE=DATE` are ignored.
+fn split_property(line: &str) -> Option<(String, &str)> {
+    let (head, value) = line.split_once(':')?;
+    let name = head.split(';').next().unwrap_or(head);
+    Some((name.to_ascii_uppercase(), value))
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+fn unescape(text: &str) -> String {
+    let mut out = String::new();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') | Some('N') => out.push('\n'),
+            Some(other) => out.push(other),
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+/// Splits a comma separated value list, honoring escaped commas.
+fn split_list(value: &str) -> Vec<String> {
+    let mut items = Vec::new();
+    let mut current = String::new();
+    let mut escaped = false;
+    for c in value.chars() {
+        if escaped {
+            current.push('\\');
+            current.push(c);
+            escaped = false;
+        } else if c == '\\' {
+            escaped = true;
+        } else if c == ',' {
+            items.push(unescape(current.trim()));
+            current.clear();
+        } else {
+            current.push(c);
+        }
+    }
+    items.push(unescape(current.trim()));
+    items
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn list() -> TodoList {
+        let mut list = TodoList::new(String::from("home"));
+        list.todos.push(Todo {
+            id: 1,
+            item: String::from("milk, eggs; bread\\butter\nand jam"),
+            priority: 12,
+            tags: vec![String::from("+shop"), String::from("a,b")],
+            done: true,
+            created_at: Some(1_704_067_200),
+            modified_at: Some(1_704_070_800),
+            completed_at: Some(1_704_074_400),
+            due_at: Some(1_704_153_600),
+            uid: None,
+        });
+        list.todos.push(Todo {
+            id: 2,
+            item: String::from("call"),
+            uid: Some(String::from("abc@example.com")),
+            ..Default::default()
+        });
+        list
+    }
+
+    #[test]
+    fn round_trips() {
+        let mut list = list();
+        assert!(assign_uids(&mut list));
+        assert!(!assign_uids(&mut list));
+        let todos = parse(&serialize(&[list.clone()]));
+        assert_eq!(todos.len(), 2);
+
+        let todo = &todos[0];
+        assert_eq!(todo.uid, list.todos[0].uid);
+        assert_eq!(todo.item, "milk, eggs; bread\\butter\nand jam");
+        assert_eq!(todo.priority, 9);
+        assert_eq!(todo.tags, ["+shop", "a,b"]);
+        assert!(todo.done);
+        assert_eq!(todo.created_at, Some(1_704_067_200));
+        assert_eq!(todo.modified_at, Some(1_704_070_800));
+        assert_eq!(todo.completed_at, Some(1_704_074_400));
+        assert_eq!(todo.due_at, Some(1_704_153_600));
+
+        let todo = &todos[1];
+        assert_eq!(todo.uid.as_deref(), Some("abc@example.com"));
+        assert!(!todo.done);
+        assert_eq!(todo.priority, 0);
+    }
+
+    #[test]
+    fn folds_long_lines() {
+        let mut list = TodoList::new(String::from("home"));
+        list.todos.push(Todo::new(1, "é".repeat(100)));
+        let data = serialize(&[list]);
+
+        assert!(data.split("\r\n").all(|line| line.len() <= MAX_LINE_LENGTH));
+        assert_eq!(parse(&data)[0].item, "é".repeat(100));
+    }
+
+    #[test]
+    fn merging_updates_items_with_the_same_uid() {
+        let mut list = list();
+        assign_uids(&mut list);
+        let mut todos = parse(&serialize(&[list.clone()]));
+        todos[0].item = String::from("changed");
+        todos.push(Todo {
+            item: String::from("new"),
+            ..Default::default()
+        });
+
+        merge(&mut list, todos);
+        let items: Vec<(i16, &str)> = list
+            .todos
+            .iter()
+            .map(|todo| (todo.id, todo.item.as_str()))
+            .collect();
+        assert_eq!(items, [(1, "changed"), (2, "call"), (3, "new")]);
+    }
+
+    #[test]
+    fn merging_ignores_todos_that_reuse_an_id() {
+        let mut list = TodoList::new(String::from("home"));
+        list.todos.push(Todo::new(1, String::from("old")));
+        assign_uids(&mut list);
+        let data = serialize(&[list.clone()]);
+
+        list.todos.clear();
+        list.todos.push(Todo::new(1, String::from("unrelated new")));
+        assign_uids(&mut list);
+        merge(&mut list, parse(&data));
+
+        let items: Vec<&str> = list.todos.iter().map(|todo| todo.item.as_str()).collect();
+        assert_eq!(items, ["unrelated new", "old"]);
+    }
+
+    #[test]
+    fn reads_parameters_and_ignores_other_components() {
+        let data = "BEGIN:VCALENDAR\r\n\
+                    BEGIN:VEVENT\r\nSUMMARY:meeting\r\nEND:VEVENT\r\n\
+                    BEGIN:VTODO\r\nsummary;LANGUAGE=en:water\r\n plants\r\n\
+                    DUE;VALUE=DATE:20240102\r\nEND:VTODO\r\n\
+                    END:VCALENDAR\r\n";
+        let todos = parse(data);
+        assert_eq!(todos.len(), 1);
+        assert_eq!(todos[0].item, "waterplants");
+        assert_eq!(todos[0].due_at, date::parse_date("2024-01-02"));
+    }
+
+    #[test]
+    fn ignores_invalid_dates() {
+        let data = "BEGIN:VTODO\r\nSUMMARY:x\r\nDUE:202é010\r\nEND:VTODO\r\n";
+        assert_eq!(parse(data)[0].due_at, None);
+    }
+}
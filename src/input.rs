@@ -0,0 +1,136 @@
+//! The line of text typed into the panel's prompts, edited with the keys
+//! the panel forwards while it isn't in its normal mode.
+
+use termion::event::Key;
+
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct Input {
+    text: String,
+    /// Position of the cursor, in characters.
+    cursor: usize,
+}
+
+impl Input {
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Applies an editing key, returns whether the key edits text.
+    pub fn edit(&mut self, key: Key) -> bool {
+        match key {
+            Key::Char(c) if c != '\n' && c != '\t' => {
+                let at = self.byte_index(self.cursor);
+                self.text.insert(at, c);
+                self.cursor += 1;
+            }
+            Key::Backspace if self.cursor > 0 => {
+                self.cursor -= 1;
+                let at = self.byte_index(self.cursor);
+                self.text.remove(at);
+            }
+            Key::Delete if self.cursor < self.len() => {
+                let at = self.byte_index(self.cursor);
+                self.text.remove(at);
+            }
+            Key::Left if self.cursor > 0 => self.cursor -= 1,
+            Key::Right if self.cursor < self.len() => self.cursor += 1,
+            Key::Home | Key::Ctrl('a') => self.cursor = 0,
+            Key::End | Key::Ctrl('e') => self.cursor = self.len(),
+            Key::Ctrl('u') => {
+                let at = self.byte_index(self.cursor);
+                self.text.replace_range(..at, "");
+                self.cursor = 0;
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    /// The part of the text that fits in `width` columns around the cursor,
+    /// and the column of the cursor in it.
+    pub fn view(&self, width: usize) -> (String, usize) {
+        let width = width.max(1);
+        let start = (self.cursor + 1).saturating_sub(width);
+        let text = self.text.chars().skip(start).take(width).collect();
+        (text, self.cursor - start)
+    }
+
+    fn len(&self) -> usize {
+        self.text.chars().count()
+    }
+
+    fn byte_index(&self, cursor: usize) -> usize {
+        self.text
+            .char_indices()
+            .nth(cursor)
+            .map(|(index, _)| index)
+            .unwrap_or(self.text.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn typed(keys: &[Key]) -> Input {
+        let mut input = Input::default();
+        for key in keys {
+            input.edit(*key);
+        }
+        input
+    }
+
+    fn chars(text: &str) -> Vec<Key> {
+        text.chars().map(Key::Char).collect()
+    }
+
+    #[test]
+    fn inserts_at_the_cursor() {
+        let mut keys = chars("héllo");
+        keys.extend([
+            Key::Left,
+            Key::Left,
+            Key::Char('X'),
+            Key::Home,
+            Key::Char('>'),
+        ]);
+        let input = typed(&keys);
+        assert_eq!(input.text(), ">hélXlo");
+        assert_eq!(input.cursor, 1);
+    }
+
+    #[test]
+    fn deletes_around_the_cursor() {
+        let mut keys = chars("naïve");
+        keys.extend([Key::Left, Key::Backspace, Key::Home, Key::Delete]);
+        assert_eq!(typed(&keys).text(), "aïe");
+
+        let mut keys = chars("one two");
+        keys.extend([Key::Left, Key::Left, Key::Left, Key::Ctrl('u'), Key::End]);
+        let input = typed(&keys);
+        assert_eq!(input.text(), "two");
+        assert_eq!(input.cursor, 3);
+    }
+
+    #[test]
+    fn ignores_keys_that_dont_edit() {
+        let mut input = typed(&chars("ab"));
+        assert!(!input.edit(Key::Right));
+        assert!(!input.edit(Key::Delete));
+        assert!(!input.edit(Key::Char('\n')));
+        assert!(!input.edit(Key::Up));
+        assert!(input.edit(Key::Left));
+
+        let mut input = Input::default();
+        assert!(!input.edit(Key::Backspace));
+        assert!(!input.edit(Key::Left));
+    }
+
+    #[test]
+    fn views_the_text_around_the_cursor() {
+        let mut input = typed(&chars("abcdef"));
+        assert_eq!(input.view(4), (String::from("def"), 3));
+        input.edit(Key::Home);
+        assert_eq!(input.view(4), (String::from("abcd"), 0));
+    }
+}
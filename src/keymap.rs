@@ -0,0 +1,360 @@
+//! The table of key bindings the panel dispatches on and the help overlay
+//! is generated from. Bindings can be overridden in the settings with a map
+//! from action name to space separated keys, e.g. `"save": "s Ctrl-s"`.
+
+use crate::terminal::{INTERRUPT_KEY, SUSPEND_KEY};
+use std::collections::HashMap;
+use termion::event::Key;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    HighlightUp,
+    HighlightDown,
+    Toggle,
+    Create,
+    Edit,
+    Delete,
+    Tag,
+    Priority,
+    Mark,
+    MarkRange,
+    MoveUp,
+    MoveDown,
+    MoveTo,
+    CopyTo,
+    Save,
+    Archive,
+    ToggleArchive,
+    Restore,
+    Redraw,
+    Help,
+    Search,
+    Command,
+    Quit,
+}
+
+impl Action {
+    pub const ALL: [Action; 23] = [
+        Action::HighlightUp,
+        Action::HighlightDown,
+        Action::Toggle,
+        Action::Create,
+        Action::Edit,
+        Action::Delete,
+        Action::Tag,
+        Action::Priority,
+        Action::Mark,
+        Action::MarkRange,
+        Action::MoveUp,
+        Action::MoveDown,
+        Action::MoveTo,
+        Action::CopyTo,
+        Action::Save,
+        Action::Archive,
+        Action::ToggleArchive,
+        Action::Restore,
+        Action::Redraw,
+        Action::Help,
+        Action::Search,
+        Action::Command,
+        Action::Quit,
+    ];
+
+    /// Name of the action in the `keybindings` settings.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Action::HighlightUp => "up",
+            Action::HighlightDown => "down",
+            Action::Toggle => "toggle",
+            Action::Create => "create",
+            Action::Edit => "edit",
+            Action::Delete => "delete",
+            Action::Tag => "tag",
+            Action::Priority => "priority",
+            Action::Mark => "mark",
+            Action::MarkRange => "mark_range",
+            Action::MoveUp => "move_up",
+            Action::MoveDown => "move_down",
+            Action::MoveTo => "move_to",
+            Action::CopyTo => "copy_to",
+            Action::Save => "save",
+            Action::Archive => "archive",
+            Action::ToggleArchive => "view_archive",
+            Action::Restore => "restore",
+            Action::Redraw => "redraw",
+            Action::Help => "help",
+            Action::Search => "search",
+            Action::Command => "command",
+            Action::Quit => "quit",
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Action::HighlightUp => "Highlight the previous todo",
+            Action::HighlightDown => "Highlight the next todo",
+            Action::Toggle => "Check or uncheck",
+            Action::Create => "Add a todo",
+            Action::Edit => "Edit the highlighted todo",
+            Action::Delete => "Delete",
+            Action::Tag => "Add tags, -tag removes one",
+            Action::Priority => "Set the priority",
+            Action::Mark => "Mark the highlighted todo",
+            Action::MarkRange => "Start or end marking a range",
+            Action::MoveUp => "Move up",
+            Action::MoveDown => "Move down",
+            Action::MoveTo => "Move to another list",
+            Action::CopyTo => "Copy to another list",
+            Action::Save => "Save the list",
+            Action::Archive => "Archive completed todos",
+            Action::ToggleArchive => "Show or hide the archive",
+            Action::Restore => "Restore from the archive",
+            Action::Redraw => "Redraw the screen",
+            Action::Help => "Show or hide this help",
+            Action::Search => "Search, Enter keeps the highlighted match",
+            Action::Command => "Run a command, :messages shows past messages",
+            Action::Quit => "Quit, or leave the current mode",
+        }
+    }
+
+    /// Whether the action can be used while nothing can be edited, e.g. in
+    /// the archive.
+    pub fn is_read_only(&self) -> bool {
+        matches!(
+            self,
+            Action::HighlightUp
+                | Action::HighlightDown
+                | Action::ToggleArchive
+                | Action::Restore
+                | Action::Redraw
+                | Action::Help
+                | Action::Search
+                | Action::Quit
+        )
+    }
+}
+
+#[derive(Clone)]
+pub struct Keymap {
+    bindings: Vec<(Key, Action)>,
+}
+
+impl Keymap {
+    /// Builds the key map from the defaults and the overrides in the settings.
+    pub fn new(overrides: &HashMap<String, String>) -> Result<Self, String> {
+        let mut bindings = default_bindings();
+        let mut bound: Vec<(Key, &str)> = Vec::new();
+
+        let mut overrides: Vec<(&String, &String)> = overrides.iter().collect();
+        overrides.sort();
+        for (name, keys) in overrides {
+            let action = match Action::ALL.iter().find(|a| a.name() == name) {
+                Some(action) => *action,
+                None => return Err(format!("Unknown action '{}' in key bindings", name)),
+            };
+
+            let keys: Vec<Key> = keys
+                .split_whitespace()
+                .map(|k| parse_key(k).ok_or_else(|| format!("Unknown key '{}' for '{}'", k, name)))
+                .collect::<Result<_, _>>()?;
+            if let Some(key) = keys.iter().find(|key| is_reserved(**key)) {
+                return Err(format!(
+                    "'{}' can't be bound, it interrupts or suspends rustodo",
+                    key_name(*key)
+                ));
+            }
+
+            for key in &keys {
+                if let Some((_, other)) = bound.iter().find(|(k, _)| k == key) {
+                    return Err(format!(
+                        "'{}' is bound to both '{}' and '{}'",
+                        key_name(*key),
+                        other,
+                        name
+                    ));
+                }
+                bound.push((*key, name));
+            }
+
+            bindings.retain(|(key, a)| *a != action && !keys.contains(key));
+            bindings.extend(keys.into_iter().map(|key| (key, action)));
+        }
+
+        Ok(Keymap { bindings })
+    }
+
+    pub fn action(&self, key: Key) -> Option<Action> {
+        self.bindings
+            .iter()
+            .find(|(k, _)| *k == key)
+            .map(|(_, action)| *action)
+    }
+
+    /// The keys bound to an action, joined for display.
+    pub fn keys(&self, action: Action) -> String {
+        let keys: Vec<String> = self
+            .bindings
+            .iter()
+            .filter(|(_, a)| *a == action)
+            .map(|(key, _)| key_name(*key))
+            .collect();
+        keys.join("/")
+    }
+}
+
+fn is_reserved(key: Key) -> bool {
+    key == Key::Ctrl(INTERRUPT_KEY) || key == Key::Ctrl(SUSPEND_KEY)
+}
+
+fn default_bindings() -> Vec<(Key, Action)> {
+    vec![
+        (Key::Up, Action::HighlightUp),
+        (Key::Down, Action::HighlightDown),
+        (Key::Char('\n'), Action::Toggle),
+        (Key::Char('a'), Action::Create),
+        (Key::Char('e'), Action::Edit),
+        (Key::Char('d'), Action::Delete),
+        (Key::Char('t'), Action::Tag),
+        (Key::Char('p'), Action::Priority),
+        (Key::Char(' '), Action::Mark),
+        (Key::Char('V'), Action::MarkRange),
+        (Key::Left, Action::MoveUp),
+        (Key::Right, Action::MoveDown),
+        (Key::Char('m'), Action::MoveTo),
+        (Key::Char('c'), Action::CopyTo),
+        (Key::Char('s'), Action::Save),
+        (Key::Char('A'), Action::Archive),
+        (Key::Char('v'), Action::ToggleArchive),
+        (Key::Char('u'), Action::Restore),
+        (Key::Char('r'), Action::Redraw),
+        (Key::Char('?'), Action::Help),
+        (Key::Char('/'), Action::Search),
+        (Key::Char(':'), Action::Command),
+        (Key::Char('q'), Action::Quit),
+        (Key::Esc, Action::Quit),
+    ]
+}
+
+pub fn key_name(key: Key) -> String {
+    match key {
+        Key::Char('\n') => String::from("Enter"),
+        Key::Char(' ') => String::from("Space"),
+        Key::Char('\t') => String::from("Tab"),
+        Key::Char(c) => c.to_string(),
+        Key::Ctrl(c) => format!("Ctrl-{}", c),
+        Key::Alt(c) => format!("Alt-{}", c),
+        Key::F(n) => format!("F{}", n),
+        Key::Up => String::from("Up"),
+        Key::Down => String::from("Down"),
+        Key::Left => String::from("Left"),
+        Key::Right => String::from("Right"),
+        Key::Esc => String::from("Esc"),
+        Key::Backspace => String::from("Backspace"),
+        Key::Delete => String::from("Delete"),
+        Key::Home => String::from("Home"),
+        Key::End => String::from("End"),
+        Key::PageUp => String::from("PageUp"),
+        Key::PageDown => String::from("PageDown"),
+        Key::Insert => String::from("Insert"),
+        other => format!("{:?}", other),
+    }
+}
+
+pub fn parse_key(name: &str) -> Option<Key> {
+    let mut chars = name.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return Some(Key::Char(c));
+    }
+
+    let single = |text: &str| {
+        let mut chars = text.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => Some(c),
+            _ => None,
+        }
+    };
+
+    if let Some(c) = name.strip_prefix("Ctrl-").and_then(single) {
+        return Some(Key::Ctrl(c));
+    }
+    if let Some(c) = name.strip_prefix("Alt-").and_then(single) {
+        return Some(Key::Alt(c));
+    }
+    if let Some(n) = name.strip_prefix('F').and_then(|n| n.parse().ok()) {
+        return Some(Key::F(n));
+    }
+
+    match name {
+        "Enter" => Some(Key::Char('\n')),
+        "Space" => Some(Key::Char(' ')),
+        "Tab" => Some(Key::Char('\t')),
+        "Up" => Some(Key::Up),
+        "Down" => Some(Key::Down),
+        "Left" => Some(Key::Left),
+        "Right" => Some(Key::Right),
+        "Esc" => Some(Key::Esc),
+        "Backspace" => Some(Key::Backspace),
+        "Delete" => Some(Key::Delete),
+        "Home" => Some(Key::Home),
+        "End" => Some(Key::End),
+        "PageUp" => Some(Key::PageUp),
+        "PageDown" => Some(Key::PageDown),
+        "Insert" => Some(Key::Insert),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn overrides(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(name, keys)| (name.to_string(), keys.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn parses_key_names() {
+        assert_eq!(parse_key("x"), Some(Key::Char('x')));
+        assert_eq!(parse_key("Enter"), Some(Key::Char('\n')));
+        assert_eq!(parse_key("Space"), Some(Key::Char(' ')));
+        assert_eq!(parse_key("Ctrl-s"), Some(Key::Ctrl('s')));
+        assert_eq!(parse_key("Alt-é"), Some(Key::Alt('é')));
+        assert_eq!(parse_key("F5"), Some(Key::F(5)));
+        assert_eq!(parse_key("PageDown"), Some(Key::PageDown));
+        assert_eq!(parse_key("Ctrl-"), None);
+        assert_eq!(parse_key("Ctrl-ab"), None);
+        assert_eq!(parse_key("Fx"), None);
+        assert_eq!(parse_key("enter"), None);
+    }
+
+    #[test]
+    fn key_names_parse_back() {
+        for (key, _) in default_bindings() {
+            assert_eq!(parse_key(&key_name(key)), Some(key));
+        }
+    }
+
+    #[test]
+    fn overrides_replace_the_default_keys() {
+        let keymap = Keymap::new(&overrides(&[("save", "w Ctrl-s"), ("quit", "s")])).unwrap();
+        assert_eq!(keymap.action(Key::Char('w')), Some(Action::Save));
+        assert_eq!(keymap.action(Key::Ctrl('s')), Some(Action::Save));
+        assert_eq!(keymap.action(Key::Char('s')), Some(Action::Quit));
+        assert_eq!(keymap.action(Key::Char('q')), None);
+        assert_eq!(keymap.keys(Action::Save), "w/Ctrl-s");
+    }
+
+    #[test]
+    fn rejects_bad_overrides() {
+        assert!(Keymap::new(&overrides(&[("nope", "x")])).is_err());
+        assert!(Keymap::new(&overrides(&[("save", "Ctrl-")])).is_err());
+        assert!(Keymap::new(&overrides(&[("save", "Ctrl-c")])).is_err());
+        assert_eq!(
+            Keymap::new(&overrides(&[("save", "x"), ("delete", "y x")])).err(),
+            Some(String::from("'x' is bound to both 'delete' and 'save'"))
+        );
+    }
+}
@@ -0,0 +1,41 @@
+//! Todo lists kept by a storage backend, a folder of files by default.
+//!
+//! Lists are loaded and saved through a [`storage::Storage`], changed through
+//! the methods of [`TodoList`] and [`Todo`] or the operations in [`store`],
+//! and queried with [`stats`] or exported with [`export`]. Every fallible
+//! function returns an [`Error`].
+//!
+//! ```no_run
+//! use rustodo::{storage, Settings, Todo};
+//!
+//! let settings = Settings::load()?;
+//! let storage = storage::open(&settings)?;
+//! let mut list = storage.load("groceries")?;
+//!
+//! list.todos.push(Todo::new(list.next_id(), String::from("Milk")));
+//! println!("{} of {} done", list.completed(), list.total());
+//!
+//! storage.save(&list)?;
+//! # Ok::<(), rustodo::Error>(())
+//! ```
+
+pub mod archive;
+pub mod date;
+pub mod error;
+pub mod export;
+pub mod ical;
+pub mod lists;
+pub mod merge;
+pub mod settings;
+pub mod stats;
+pub mod storage;
+pub mod store;
+pub mod todo;
+pub mod todotxt;
+
+#[cfg(test)]
+mod testing;
+
+pub use error::{Error, Result};
+pub use settings::Settings;
+pub use todo::{Format, Todo, TodoChanges, TodoList};
@@ -0,0 +1,90 @@
+//! Lookup of todo lists by name.
+
+use crate::error::{Error, Result};
+use std::fs::read_dir;
+
+/// Names of all lists in the todo path, sorted and without extension, and
+/// warnings about files that look like lists but can't be opened by name.
+pub fn scan(dir_path: &str) -> Result<(Vec<String>, Vec<String>)> {
+    let entries = match read_dir(dir_path) {
+        Ok(entries) => entries,
+        Err(err) => return Err(Error::io("read", dir_path, err)),
+    };
+
+    let mut names = Vec::new();
+    let mut warnings = Vec::new();
+
+    for entry in entries {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(err) => {
+                warnings.push(format!("Unable to read directory entry: {}", err));
+                continue;
+            }
+        };
+
+        match entry.file_type() {
+            Ok(file_type) if file_type.is_file() => {}
+            Ok(_) => continue,
+            Err(err) => {
+                warnings.push(format!(
+                    "Unable to read '{}': {}",
+                    entry.file_name().to_string_lossy(),
+                    err
+                ));
+                continue;
+            }
+        }
+
+        let file_name = match entry.file_name().into_string() {
+            Ok(file_name) => file_name,
+            Err(file_name) => {
+                warnings.push(format!(
+                    "Skipping '{}': the file name is not valid UTF-8",
+                    file_name.to_string_lossy()
+                ));
+                continue;
+            }
+        };
+
+        if let Some(name) = file_name
+            .strip_suffix(".json")
+            .or_else(|| file_name.strip_suffix(".txt"))
+        {
+            names.push(name.to_string());
+        }
+    }
+
+    names.sort();
+    names.dedup();
+    Ok((names, warnings))
+}
+
+/// Checks that a list name can be used as a file name in the todo path: it
+/// can't be empty, start with a dot (like `..` or `.archive`), or contain a
+/// path separator or NUL.
+pub fn validate(name: &str) -> Result<()> {
+    if name.is_empty() || name.starts_with('.') || name.contains(['/', '\\', '\0']) {
+        return Err(Error::InvalidName(name.to_string()));
+    }
+    Ok(())
+}
+
+/// Completes a (possibly partial) list name against the existing lists.
+pub fn complete(names: &[String], input: &str) -> Result<String> {
+    let input = input.trim();
+
+    if names.iter().any(|name| name == input) {
+        return Ok(input.to_string());
+    }
+
+    let matches: Vec<&String> = names.iter().filter(|n| n.starts_with(input)).collect();
+    match matches.as_slice() {
+        [name] => Ok(name.to_string()),
+        [] => Err(Error::ListNotFound(input.to_string())),
+        _ => Err(Error::AmbiguousList {
+            input: input.to_string(),
+            matches: matches.into_iter().cloned().collect(),
+        }),
+    }
+}
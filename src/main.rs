@@ -1,42 +1,188 @@
+mod backend;
+mod completions;
+mod daemon;
 mod debug;
 mod draw;
+mod frame;
+mod input;
+mod keymap;
+mod messages;
 mod panel;
 mod reader;
-mod settings;
-mod todo;
+mod replay;
+mod report;
+mod server;
+mod terminal;
+#[cfg(test)]
+mod testing;
+mod timers;
 
-use std::fs::{read_dir, File};
-use std::io::Read;
+use std::cmp::Reverse;
+use std::io::{stdin, stdout, Write};
 use std::{env, fs};
 
-pub use crate::panel::Panel;
-pub use crate::settings::Settings;
-pub use crate::todo::{Todo, TodoList};
+use rustodo::export::{self, Column, ExportFormat};
+use rustodo::storage::{self, Git, Query, Storage};
+use rustodo::store;
+use rustodo::{archive, date, ical, lists, stats, Settings, TodoList};
+
+use crate::keymap::Keymap;
+use crate::panel::Panel;
+use crate::replay::{Dump, ReplayOptions};
+
+const PROGRESS_WIDTH: u16 = 20;
+
+enum ListSort {
+    Name,
+    Progress,
+    Modified,
+    Open,
+}
+
+struct ListOptions {
+    sort: ListSort,
+    json: bool,
+}
+
+struct ExportOptions {
+    /// `None` exports every list in the todo path.
+    list: Option<String>,
+    format: ExportFormat,
+    columns: Vec<Column>,
+    file: Option<String>,
+}
 
 enum Command {
     OpenListPanel(String),
     NewList(String),
-    ListLists,
+    ListLists(ListOptions),
+    Import(String, Option<String>),
+    Export(ExportOptions),
+    Archive(String),
+    Stats(Option<String>),
+    Find(Query),
+    MoveTodo(String, i16, String),
+    RenameList(String, String),
+    RemoveList(String, bool),
+    CopyList(String, String),
+    ResetList(String),
+    Sync,
+    History(String),
+    /// The port and the origins allowed to use the API from a browser.
+    Serve(u16, Vec<String>),
+    Daemon(Option<String>),
+    Completions(String),
+    /// Prints the list names for the completion scripts.
+    CompleteLists,
+    Replay(ReplayOptions),
 }
 
 fn main() {
     let settings = load_settings().unwrap();
+    let storage = match storage::open(&settings) {
+        Ok(storage) => storage,
+        Err(err) => return println!("{}", err),
+    };
+    let storage = storage.as_ref();
 
     match parse_command() {
         Ok(cmd) => match cmd {
-            Command::ListLists => {
-                list_todo_lists(&settings).unwrap_or_else(|err| {
+            Command::ListLists(options) => {
+                list_todo_lists(storage, options).unwrap_or_else(|err| {
                     println!("{}", err);
                 });
             }
-            Command::NewList(name) => match create_todo_list(&settings, name) {
-                Ok(list) => Panel::new(list, settings).start(),
+            Command::NewList(name) => match store::create(storage, name) {
+                Ok(list) => Panel::new(list, settings, storage).start(),
+                Err(err) => println!("{}", err),
+            },
+            Command::OpenListPanel(name) => match storage.load(&name) {
+                Ok(list) => Panel::new(list, settings, storage).start(),
+                Err(err) => println!("{}", err),
+            },
+            Command::Import(file, name) => match store::import(storage, &file, name) {
+                Ok((list, count)) => println!("Imported {} todos into '{}'", count, list.name),
+                Err(err) => println!("{}", err),
+            },
+            Command::Archive(name) => match archive_todo_list(storage, name) {
+                Ok((list, count)) => println!("Archived {} todos from '{}'", count, list.name),
+                Err(err) => println!("{}", err),
+            },
+            Command::MoveTodo(source, id, target) => {
+                match store::move_todo(storage, &source, id, &target) {
+                    Ok((todo, target)) => println!("Moved '{}' to '{}'", todo.item, target.name),
+                    Err(err) => println!("{}", err),
+                }
+            }
+            Command::RenameList(name, new_name) => match store::rename(storage, &name, new_name) {
+                Ok((old, list)) => println!("Renamed '{}' to '{}'", old, list.name),
+                Err(err) => println!("{}", err),
+            },
+            Command::RemoveList(name, confirmed) => {
+                match remove_todo_list(storage, name, confirmed) {
+                    Ok(Some(list)) => println!("Moved '{}' to the trash", list.name),
+                    Ok(None) => println!("Nothing was removed"),
+                    Err(err) => println!("{}", err),
+                }
+            }
+            Command::CopyList(name, new_name) => match store::copy(storage, &name, new_name) {
+                Ok(list) => println!("Copied {} todos into '{}'", list.total(), list.name),
+                Err(err) => println!("{}", err),
+            },
+            Command::ResetList(name) => match store::reset(storage, &name) {
+                Ok((list, count)) => println!("Unchecked {} todos in '{}'", count, list.name),
+                Err(err) => println!("{}", err),
+            },
+            Command::Stats(name) => {
+                print_stats(storage, name).unwrap_or_else(|err| {
+                    println!("{}", err);
+                });
+            }
+            Command::Find(query) => {
+                find_todos(&settings, storage, &query).unwrap_or_else(|err| {
+                    println!("{}", err);
+                });
+            }
+            Command::Export(options) => {
+                export_todo_lists(storage, options).unwrap_or_else(|err| {
+                    println!("{}", err);
+                });
+            }
+            Command::Serve(port, origins) => {
+                server::serve(storage, port, &origins).unwrap_or_else(|err| {
+                    println!("{}", err);
+                });
+            }
+            Command::Daemon(socket) => {
+                let socket = socket
+                    .unwrap_or_else(|| format!("{}/{}", settings.todopath, daemon::DEFAULT_SOCKET));
+                daemon::run(storage, &socket).unwrap_or_else(|err| {
+                    println!("{}", err);
+                });
+            }
+            Command::Completions(shell) => match completions::script(&shell) {
+                Ok(script) => print!("{}", script),
+                Err(err) => println!("{}", err),
+            },
+            Command::CompleteLists => {
+                // Completions stay quiet about errors
+                for name in storage.names().unwrap_or_default() {
+                    println!("{}", name);
+                }
+            }
+            Command::Replay(options) => match replay::run(settings, options) {
+                Ok(dump) => print!("{}", dump),
                 Err(err) => println!("{}", err),
             },
-            Command::OpenListPanel(name) => match open_todo_list(&settings, name) {
-                Ok(list) => Panel::new(list, settings).start(),
+            Command::Sync => match sync_todo_lists(&settings) {
+                Ok(remote) => println!("Synced with '{}'", remote),
                 Err(err) => println!("{}", err),
             },
+            Command::History(name) => {
+                print_history(&settings, storage, &name).unwrap_or_else(|err| {
+                    println!("{}", err);
+                });
+            }
         },
         Err(err) => println!("Unable to parse command: {}", err),
     };
@@ -51,101 +197,527 @@ fn parse_command() -> Result<Command, String> {
     }
 
     if args[0] == "new" {
-        if args.len() < 2 || args[1].trim().len() == 0 {
-            return Err("Please provide a valid list name".into());
-        } else {
-            return Ok(Command::NewList(args[1].trim().to_string()));
+        return Ok(Command::NewList(list_name_arg(&args, 1)?));
+    }
+
+    if args[0] == "import" {
+        if args.len() < 2 {
+            return Err("Please provide the todo.txt or .ics file to import".into());
+        }
+        let name = match args.len() {
+            2 => None,
+            _ => Some(list_name_arg(&args, 2)?),
+        };
+        return Ok(Command::Import(args[1].clone(), name));
+    }
+
+    if args[0] == "archive" {
+        return Ok(Command::Archive(list_name_arg(&args, 1)?));
+    }
+
+    if args[0] == "rename" {
+        return Ok(Command::RenameList(
+            list_name_arg(&args, 1)?,
+            list_name_arg(&args, 2)?,
+        ));
+    }
+
+    if args[0] == "rm" {
+        let confirmed = args.iter().any(|a| a == "-y" || a == "--yes");
+        let rest: Vec<String> = args
+            .iter()
+            .filter(|a| *a != "-y" && *a != "--yes")
+            .cloned()
+            .collect();
+        return Ok(Command::RemoveList(list_name_arg(&rest, 1)?, confirmed));
+    }
+
+    if args[0] == "cp" {
+        return Ok(Command::CopyList(
+            list_name_arg(&args, 1)?,
+            list_name_arg(&args, 2)?,
+        ));
+    }
+
+    if args[0] == "reset" {
+        return Ok(Command::ResetList(list_name_arg(&args, 1)?));
+    }
+
+    if args[0] == "mv" {
+        if args.len() < 4 {
+            return Err("Usage: rustodo mv <list> <id> <target list>".into());
         }
+        let id = match args[2].parse() {
+            Ok(id) => id,
+            Err(_) => return Err(format!("Invalid todo id '{}'", args[2])),
+        };
+        return Ok(Command::MoveTodo(
+            list_name_arg(&args, 1)?,
+            id,
+            list_name_arg(&args, 3)?,
+        ));
+    }
+
+    if args[0] == "find" {
+        return parse_find(&args[1..]);
+    }
+
+    if args[0] == "serve" {
+        return parse_serve(&args[1..]);
+    }
+
+    if args[0] == "daemon" {
+        return match args.get(1).map(|a| a.as_str()) {
+            None => Ok(Command::Daemon(None)),
+            Some("--socket") => match args.get(2) {
+                Some(socket) => Ok(Command::Daemon(Some(socket.clone()))),
+                None => Err("Please provide the socket path".into()),
+            },
+            Some(other) => Err(format!("Unexpected argument '{}'", other)),
+        };
+    }
+
+    if args[0] == "completions" {
+        return match args.get(1) {
+            Some(shell) => Ok(Command::Completions(shell.clone())),
+            None => Err("Please provide a shell (bash, zsh or fish)".into()),
+        };
+    }
+
+    if args[0] == "--replay" {
+        return parse_replay(&args[1..]);
+    }
+
+    if args[0] == "__lists" {
+        return Ok(Command::CompleteLists);
+    }
+
+    if args[0] == "sync" {
+        return Ok(Command::Sync);
+    }
+
+    if args[0] == "history" {
+        return Ok(Command::History(list_name_arg(&args, 1)?));
+    }
+
+    if args[0] == "stats" {
+        return Ok(Command::Stats(args.get(1).map(|n| n.trim().to_string())));
+    }
+
+    if args[0] == "export" {
+        return parse_export(&args[1..]);
     }
 
     if args[0] == "list" {
-        Ok(Command::ListLists)
+        parse_list(&args[1..])
     } else {
         Ok(Command::OpenListPanel(args[0].trim().into()))
     }
 }
 
-fn list_todo_lists(settings: &Settings) -> Result<(), String> {
-    let path = &settings.todopath;
+/// Parses `serve [--port N] [--origin URL]...`.
+fn parse_serve(args: &[String]) -> Result<Command, String> {
+    let mut port = server::DEFAULT_PORT;
+    let mut origins = Vec::new();
 
-    let iter = match read_dir(path) {
-        Ok(entries) => entries,
-        Err(err) => {
-            return Err(format!(
-                "Unable to read todo lists at path '{}': {}",
-                path, err
-            ))
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--port" => {
+                port = match iter.next().map(|p| p.parse()) {
+                    Some(Ok(port)) => port,
+                    Some(Err(_)) => return Err("Please provide a valid port".into()),
+                    None => return Err("Please provide a port".into()),
+                }
+            }
+            "--origin" => match iter.next() {
+                Some(origin) => origins.push(origin.trim_end_matches('/').to_string()),
+                None => return Err("Please provide an origin, e.g. http://localhost:3000".into()),
+            },
+            other => return Err(format!("Unexpected argument '{}'", other)),
         }
+    }
+
+    Ok(Command::Serve(port, origins))
+}
+
+/// Parses `--replay <keys file> <list> [--dump screen|list]`.
+fn parse_replay(args: &[String]) -> Result<Command, String> {
+    let mut dump = Dump::Screen;
+    let mut positional = Vec::new();
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--dump" => {
+                dump = match iter.next().map(|d| d.as_str()) {
+                    Some("screen") => Dump::Screen,
+                    Some("list") => Dump::List,
+                    Some(other) => return Err(format!("Unknown dump '{}'", other)),
+                    None => return Err("Please provide what to dump (screen or list)".into()),
+                }
+            }
+            _ => positional.push(arg.trim().to_string()),
+        }
+    }
+
+    match positional.as_slice() {
+        [keys, list] => Ok(Command::Replay(ReplayOptions {
+            keys: keys.clone(),
+            list: list.clone(),
+            dump,
+        })),
+        _ => Err("Usage: rustodo --replay <keys file> <list> [--dump screen|list]".into()),
+    }
+}
+
+/// Parses `list [--sort name|progress|modified|open] [--json]`.
+fn parse_list(args: &[String]) -> Result<Command, String> {
+    let mut options = ListOptions {
+        sort: ListSort::Name,
+        json: false,
     };
 
-    for file in iter {
-        let entry = match file {
-            Ok(entry) => entry,
-            _ => continue,
-        };
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--json" => options.json = true,
+            "--sort" => {
+                options.sort = match iter.next().map(|s| s.as_str()) {
+                    Some("name") => ListSort::Name,
+                    Some("progress") => ListSort::Progress,
+                    Some("modified") => ListSort::Modified,
+                    Some("open") => ListSort::Open,
+                    Some(other) => {
+                        return Err(format!(
+                            "Unknown sort '{}' (expected name, progress, modified or open)",
+                            other
+                        ))
+                    }
+                    None => return Err("Please provide a sort order".into()),
+                }
+            }
+            other => return Err(format!("Unexpected argument '{}'", other)),
+        }
+    }
+
+    Ok(Command::ListLists(options))
+}
 
-        let entry_type = entry.file_type().unwrap();
+/// Parses `find [text] [--tag tag] [--open|--done]`.
+fn parse_find(args: &[String]) -> Result<Command, String> {
+    let mut query = Query::default();
 
-        if !entry_type.is_file() {
-            continue;
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--open" => query.done = Some(false),
+            "--done" => query.done = Some(true),
+            "--tag" => match iter.next() {
+                Some(tag) => query.tag = Some(tag.trim().to_string()),
+                None => return Err("Please provide a tag".into()),
+            },
+            _ if query.text.is_none() => query.text = Some(arg.trim().to_string()),
+            other => return Err(format!("Unexpected argument '{}'", other)),
         }
+    }
 
-        let entry_name = entry.file_name().to_str().to_owned().unwrap().to_string();
+    Ok(Command::Find(query))
+}
 
-        let todo_list = match open_todo_list(settings, entry_name.to_string()) {
-            Ok(todo_list) => todo_list,
-            Err(_) => continue,
-        };
+fn list_name_arg(args: &[String], index: usize) -> Result<String, String> {
+    match args.get(index).map(|arg| arg.trim()) {
+        Some(name) if !name.is_empty() => match lists::validate(name) {
+            Ok(_) => Ok(name.to_string()),
+            Err(err) => Err(err.to_string()),
+        },
+        _ => Err("Please provide a valid list name".into()),
+    }
+}
+
+/// Parses `export <list|--all> [--format txt|csv|jsonl] [--columns a,b] [file]`.
+fn parse_export(args: &[String]) -> Result<Command, String> {
+    let mut list = None;
+    let mut all = false;
+    let mut format = ExportFormat::TodoTxt;
+    let mut columns = Column::ALL.to_vec();
+    let mut file = None;
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--all" => all = true,
+            "--format" => match iter.next() {
+                Some(name) => format = ExportFormat::parse(name).map_err(|err| err.to_string())?,
+                None => return Err("Please provide an export format".into()),
+            },
+            "--columns" => match iter.next() {
+                Some(names) => {
+                    columns = Column::parse_list(names).map_err(|err| err.to_string())?
+                }
+                None => return Err("Please provide the columns to export".into()),
+            },
+            _ if list.is_none() && !all => list = Some(arg.trim().to_string()),
+            _ if file.is_none() => file = Some(arg.clone()),
+            other => return Err(format!("Unexpected argument '{}'", other)),
+        }
+    }
 
-        println!(
-            "{}: {}/{}",
-            todo_list.name,
-            todo_list.completed(),
-            todo_list.total()
+    if all && list.is_some() {
+        return Err("Please provide either a list name or --all, not both".into());
+    }
+
+    if all && format == ExportFormat::TodoTxt {
+        // A todo.txt file has no place for the names of the lists
+        return Err(
+            "todo.txt exports hold a single list, use --format csv, jsonl or ics with --all".into(),
         );
     }
 
+    if !all && list.as_ref().is_none_or(|l| l.is_empty()) {
+        return Err("Please provide a valid list name or --all".into());
+    }
+
+    Ok(Command::Export(ExportOptions {
+        list,
+        format,
+        columns,
+        file,
+    }))
+}
+
+fn list_todo_lists(storage: &dyn Storage, options: ListOptions) -> rustodo::Result<()> {
+    let (mut summaries, warnings) = storage.summaries()?;
+
+    match options.sort {
+        ListSort::Name => summaries.sort_by(|a, b| a.name.cmp(&b.name)),
+        ListSort::Progress => summaries.sort_by(|a, b| a.progress().total_cmp(&b.progress())),
+        ListSort::Modified => summaries.sort_by_key(|s| Reverse(s.modified)),
+        ListSort::Open => summaries.sort_by_key(|s| Reverse(s.open())),
+    }
+
+    if options.json {
+        let lists: Vec<serde_json::Value> = summaries
+            .iter()
+            .map(|s| {
+                serde_json::json!({
+                    "name": s.name,
+                    "format": s.format.extension(),
+                    "done": s.done,
+                    "open": s.open(),
+                    "total": s.total,
+                    "progress": s.progress(),
+                    "tags": s.tags,
+                    "modified": s.modified.map(date::format_datetime),
+                })
+            })
+            .collect();
+        let out = serde_json::json!({ "lists": lists, "warnings": warnings });
+        println!("{}", out);
+        return Ok(());
+    }
+
+    for warning in warnings {
+        eprintln!("{}", draw::warning(format!("Warning: {}", warning)));
+    }
+
+    let mut rows = vec![vec![
+        String::from("NAME"),
+        String::from("DONE"),
+        String::from("OPEN"),
+        String::from("TOTAL"),
+        String::from("PROGRESS"),
+        String::new(),
+        String::from("MODIFIED"),
+    ]];
+    for summary in summaries.iter() {
+        let filled = draw::bar(summary.done, summary.total, PROGRESS_WIDTH);
+        let empty = "░".repeat((PROGRESS_WIDTH - draw::visible_length(&filled)) as usize);
+        rows.push(vec![
+            summary.name.clone(),
+            summary.done.to_string(),
+            summary.open().to_string(),
+            summary.total.to_string(),
+            format!("{}{}", filled, empty),
+            format!("{:.0}%", summary.progress() * 100.0),
+            summary
+                .modified
+                .map(date::format_datetime)
+                .unwrap_or_default(),
+        ]);
+    }
+    print!(
+        "{}",
+        draw::table(&rows, &[false, true, true, true, false, true, false])
+    );
+
     Ok(())
 }
 
-fn create_todo_list(settings: &Settings, name: String) -> Result<TodoList, String> {
-    let created_list = TodoList::new(name);
+/// Loads every list in the todo path, printing warnings for the files that
+/// couldn't be loaded.
+fn load_todo_lists(storage: &dyn Storage) -> rustodo::Result<Vec<TodoList>> {
+    let (entries, warnings) = store::scan(storage)?;
+
+    for warning in warnings {
+        eprintln!("{}", draw::warning(format!("Warning: {}", warning)));
+    }
+
+    Ok(entries.into_iter().map(|entry| entry.list).collect())
+}
+
+fn export_todo_lists(storage: &dyn Storage, options: ExportOptions) -> rustodo::Result<()> {
+    let mut lists = match options.list {
+        Some(name) => vec![storage.load(&name)?],
+        None => load_todo_lists(storage)?,
+    };
+    if options.format == ExportFormat::ICalendar {
+        // Imports of the calendar find the todos by their UIDs
+        for list in lists.iter_mut() {
+            if ical::assign_uids(list) {
+                storage.save(list)?;
+            }
+        }
+    }
+    let data = export::export(&lists, options.format, &options.columns);
+
+    match options.file {
+        Some(file) => match fs::write(&file, data) {
+            Ok(_) => Ok(()),
+            Err(err) => Err(rustodo::Error::io("write", file, err)),
+        },
+        None => {
+            print!("{}", data);
+            Ok(())
+        }
+    }
+}
 
-    match created_list.save(&settings.todopath) {
-        Ok(_) => Ok(created_list),
-        Err(err) => Err(err),
+/// Moves a list (and its archive) into the trash folder of the todo path.
+/// Returns `None` if the removal wasn't confirmed.
+fn remove_todo_list(
+    storage: &dyn Storage,
+    name: String,
+    confirmed: bool,
+) -> rustodo::Result<Option<TodoList>> {
+    if !confirmed {
+        let list = storage.load(&name)?;
+        print!("Move list '{}' to the trash? (y/n) ", list.name);
+        stdout().flush().ok();
+        let mut answer = String::new();
+        if stdin().read_line(&mut answer).is_err() || answer.trim() != "y" {
+            return Ok(None);
+        }
     }
+
+    store::remove(storage, &name).map(Some)
 }
 
-fn open_todo_list(settings: &Settings, name: String) -> Result<TodoList, String> {
-    let path = format!("{}/{}.json", &settings.todopath, &name.replace(".json", ""));
+fn archive_todo_list(storage: &dyn Storage, name: String) -> rustodo::Result<(TodoList, usize)> {
+    let mut list = storage.load(&name)?;
+    let count = archive::archive_completed(&mut list, storage)?;
+    Ok((list, count))
+}
 
-    let mut file = match File::open(&path) {
-        Ok(file) => file,
-        Err(err) => return Err(format!("Invalid todo list name '{}': {}", name, err)),
+/// Prints the todos of all lists that match a query.
+fn find_todos(settings: &Settings, storage: &dyn Storage, query: &Query) -> rustodo::Result<()> {
+    let rows: Vec<Vec<String>> = storage
+        .find(query)?
+        .into_iter()
+        .map(|(list, todo)| {
+            let symbol = if todo.done {
+                &settings.checked_symbol
+            } else {
+                &settings.unchecked_symbol
+            };
+            vec![
+                list,
+                todo.id.to_string(),
+                format!("{} {}", symbol, todo.item),
+            ]
+        })
+        .collect();
+
+    print!("{}", draw::table(&rows, &[false, true, false]));
+    Ok(())
+}
+
+/// Opens the git repository of the todo path, see `Settings::git`.
+fn open_git(settings: &Settings) -> Result<Git, String> {
+    if !settings.git {
+        return Err("The todo path isn't kept in git, set \"git\": true in the settings".into());
+    }
+    Git::open(&settings.todopath).map_err(|err| err.to_string())
+}
+
+/// Pulls and pushes the configured remote. Returns the remote.
+fn sync_todo_lists(settings: &Settings) -> Result<String, String> {
+    let git = open_git(settings)?;
+    let remote = match &settings.git_remote {
+        Some(remote) => remote.clone(),
+        None => return Err("No remote to sync with, set \"git_remote\" in the settings".into()),
     };
+    git.sync(&remote).map_err(|err| err.to_string())?;
+    Ok(remote)
+}
 
-    let mut data = String::new();
+/// Prints the commits that changed a list, newest first.
+fn print_history(settings: &Settings, storage: &dyn Storage, name: &str) -> Result<(), String> {
+    let git = open_git(settings)?;
+    // Removed lists have a history too
+    let name = match storage.load(name) {
+        Ok(list) => list.name,
+        Err(_) => name.to_string(),
+    };
+
+    let rows: Vec<Vec<String>> = git
+        .history(&name)
+        .map_err(|err| err.to_string())?
+        .into_iter()
+        .map(|commit| vec![commit.hash, commit.date, commit.message])
+        .collect();
+    if rows.is_empty() {
+        println!("No history for '{}'", name);
+        return Ok(());
+    }
 
-    match file.read_to_string(&mut data) {
-        Err(err) => return Err(format!("Unable to read todo file: {}", err)),
-        _ => (),
+    print!("{}", draw::table(&rows, &[false, false, false]));
+    Ok(())
+}
+
+/// Prints completion statistics of one or all lists, including their
+/// archived todos.
+fn print_stats(storage: &dyn Storage, name: Option<String>) -> rustodo::Result<()> {
+    let (title, lists) = match name {
+        Some(name) => {
+            let list = storage.load(&name)?;
+            (list.name.clone(), vec![list])
+        }
+        None => (String::from("All lists"), load_todo_lists(storage)?),
     };
 
-    match serde_json::from_str(data.as_str()) {
-        Ok(todo_list) => Ok(todo_list),
-        Err(err) => Err(format!("Unable to parse todo list: {}", err)),
+    let mut archives = Vec::new();
+    for list in lists.iter() {
+        archives.push(storage.load_archive(&list.name)?);
     }
+
+    let now = date::now();
+    let todos = lists
+        .iter()
+        .chain(archives.iter())
+        .flat_map(|l| l.todos.iter());
+    let stats = stats::collect(todos, now);
+    print!("{}", report::stats(&stats, &title, now));
+
+    Ok(())
 }
 
 fn load_settings() -> Result<Settings, String> {
-    let settings = match Settings::load() {
-        Err(err) => return Err(err),
-        Ok(s) => s,
-    };
+    let settings = Settings::load().map_err(|err| err.to_string())?;
+    Keymap::new(&settings.keybindings)?;
 
     match fs::create_dir_all(settings.todopath.clone()) {
-        Ok(_) => return Ok(settings),
-        Err(err) => return Err(format!("Unable to load todo path: {}", err)),
-    };
+        Ok(_) => Ok(settings),
+        Err(err) => Err(format!("Unable to load todo path: {}", err)),
+    }
 }
@@ -0,0 +1,230 @@
+//! Three-way merge of two versions of a list that were changed from the same
+//! version, matching todos by id. The ids of a todo.txt list are its line
+//! numbers, which shift when a line is added or removed above, so its todos
+//! are matched by their line instead.
+
+use crate::todo::{Format, Todo, TodoList};
+use crate::todotxt;
+
+/// Merges the changes of `local` into `remote`, both changed from `base`.
+/// Returns the merged list, ordered like `remote` with todos added locally at
+/// the end, and the number of conflicts.
+///
+/// A todo changed on one side takes that side's version. A todo changed
+/// differently on both sides is a conflict, `remote` keeps its version and
+/// the local one is added as a new todo. A todo removed on one side is
+/// removed unless the other side changed it.
+pub fn merge(base: &TodoList, local: &TodoList, remote: &TodoList) -> (TodoList, usize) {
+    match remote.format {
+        Format::Json => merge_by_id(base, local, remote),
+        Format::TodoTxt => (merge_by_line(base, local, remote), 0),
+    }
+}
+
+fn merge_by_id(base: &TodoList, local: &TodoList, remote: &TodoList) -> (TodoList, usize) {
+    let mut merged = remote.clone();
+    let mut added = Vec::new();
+    let mut conflicts = 0;
+
+    for todo in local.todos.iter() {
+        let before = find(base, todo.id);
+        let theirs = merged.todos.iter().position(|t| t.id == todo.id);
+
+        match (before, theirs) {
+            // Added locally, the id may be taken by a todo added remotely
+            (None, _) => added.push(todo.clone()),
+            (Some(before), _) if same(remote, before, todo) => {}
+            (Some(before), Some(index)) if same(remote, &merged.todos[index], before) => {
+                merged.todos[index] = todo.clone();
+            }
+            // Changed the same way on both sides
+            (Some(_), Some(index)) if same(remote, &merged.todos[index], todo) => {}
+            (Some(_), Some(_)) => {
+                conflicts += 1;
+                added.push(todo.clone());
+            }
+            // Removed remotely, but changed locally
+            (Some(_), None) => added.push(todo.clone()),
+        }
+    }
+
+    for todo in base.todos.iter() {
+        if find(local, todo.id).is_some() {
+            continue;
+        }
+        match merged.todos.iter().position(|t| t.id == todo.id) {
+            Some(index) if same(remote, &merged.todos[index], todo) => {
+                merged.todos.remove(index);
+            }
+            // Changed remotely, but removed locally
+            Some(_) => conflicts += 1,
+            None => {}
+        }
+    }
+
+    merged.append(added);
+    (merged, conflicts)
+}
+
+/// Merges todo.txt lists line by line: a changed todo is a removed line and
+/// an added one, so changes never conflict. Lines removed locally are
+/// removed from `remote`, and lines added locally are added at the end
+/// unless they were added remotely too.
+fn merge_by_line(base: &TodoList, local: &TodoList, remote: &TodoList) -> TodoList {
+    let mut removed = lines(base);
+    let mut added = Vec::new();
+    for todo in local.todos.iter() {
+        if !take(&mut removed, &todotxt::format_todo(todo)) {
+            added.push(todo.clone());
+        }
+    }
+
+    let mut merged = remote.clone();
+    for line in removed {
+        if let Some(index) = merged
+            .todos
+            .iter()
+            .position(|todo| todotxt::format_todo(todo) == line)
+        {
+            merged.todos.remove(index);
+        }
+    }
+
+    let mut remote_added = lines(remote);
+    for line in lines(base) {
+        take(&mut remote_added, &line);
+    }
+    added.retain(|todo| !take(&mut remote_added, &todotxt::format_todo(todo)));
+
+    merged.append(added);
+    merged
+}
+
+fn lines(list: &TodoList) -> Vec<String> {
+    list.todos.iter().map(todotxt::format_todo).collect()
+}
+
+/// Removes one occurrence of a line, returning whether there was one.
+fn take(lines: &mut Vec<String>, line: &str) -> bool {
+    match lines.iter().position(|l| l == line) {
+        Some(index) => {
+            lines.remove(index);
+            true
+        }
+        None => false,
+    }
+}
+
+/// Whether two versions of a todo are stored the same in the list's format,
+/// as a todo.txt file doesn't keep every field.
+fn same(list: &TodoList, a: &Todo, b: &Todo) -> bool {
+    match list.format {
+        Format::Json => a == b,
+        Format::TodoTxt => todotxt::format_todo(a) == todotxt::format_todo(b),
+    }
+}
+
+fn find(list: &TodoList, id: i16) -> Option<&Todo> {
+    list.todos.iter().find(|todo| todo.id == id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn list(items: &[&str]) -> TodoList {
+        let mut list = TodoList::new(String::from("test"));
+        for item in items {
+            list.todos.push(Todo::new(list.next_id(), item.to_string()));
+        }
+        list
+    }
+
+    fn items(list: &TodoList) -> Vec<&str> {
+        list.todos.iter().map(|todo| todo.item.as_str()).collect()
+    }
+
+    #[test]
+    fn takes_changes_of_either_side() {
+        let base = list(&["a", "b"]);
+        let mut local = base.clone();
+        local.todos[0].item = String::from("a2");
+        let mut remote = base.clone();
+        remote.todos[1].item = String::from("b2");
+
+        let (merged, conflicts) = merge(&base, &local, &remote);
+        assert_eq!(items(&merged), ["a2", "b2"]);
+        assert_eq!(conflicts, 0);
+    }
+
+    #[test]
+    fn same_change_on_both_sides_is_no_conflict() {
+        let base = list(&["a", "b"]);
+        let mut local = base.clone();
+        local.todos[0].toggle();
+        let remote = local.clone();
+
+        let (merged, conflicts) = merge(&base, &local, &remote);
+        assert_eq!(items(&merged), ["a", "b"]);
+        assert_eq!(conflicts, 0);
+    }
+
+    #[test]
+    fn different_changes_conflict_and_keep_both() {
+        let base = list(&["a"]);
+        let mut local = base.clone();
+        local.todos[0].item = String::from("local");
+        let mut remote = base.clone();
+        remote.todos[0].item = String::from("remote");
+
+        let (merged, conflicts) = merge(&base, &local, &remote);
+        assert_eq!(items(&merged), ["remote", "local"]);
+        assert_eq!(conflicts, 1);
+    }
+
+    #[test]
+    fn keeps_todos_added_on_both_sides() {
+        let base = list(&["a"]);
+        let mut local = base.clone();
+        local.append(vec![Todo::new(0, String::from("local"))]);
+        let mut remote = base.clone();
+        remote.append(vec![Todo::new(0, String::from("remote"))]);
+
+        let (merged, conflicts) = merge(&base, &local, &remote);
+        assert_eq!(items(&merged), ["a", "remote", "local"]);
+        assert_eq!(conflicts, 0);
+    }
+
+    #[test]
+    fn removes_todos_removed_on_one_side() {
+        let base = list(&["a", "b"]);
+        let mut local = base.clone();
+        local.todos.remove(0);
+        let remote = base.clone();
+
+        let (merged, _) = merge(&base, &local, &remote);
+        assert_eq!(items(&merged), ["b"]);
+    }
+
+    #[test]
+    fn matches_todo_txt_lines_when_ids_shift() {
+        let base = todotxt::parse(String::from("test"), "a\nb\n");
+        // A line inserted above shifts the ids of the lines below
+        let remote = todotxt::parse(String::from("test"), "new\na\nb\n");
+        let mut local = base.clone();
+        local.todos[1].done = true;
+
+        let (merged, conflicts) = merge(&base, &local, &remote);
+        assert_eq!(lines(&merged), ["new", "a", "x b"]);
+        assert_eq!(conflicts, 0);
+    }
+
+    #[test]
+    fn adds_a_todo_txt_line_added_on_both_sides_once() {
+        let base = todotxt::parse(String::from("test"), "a\n");
+        let both = todotxt::parse(String::from("test"), "a\nb\n");
+
+        let (merged, _) = merge(&base, &both, &both);
+        assert_eq!(lines(&merged), ["a", "b"]);
+    }
+}
@@ -0,0 +1,130 @@
+//! Notifications shown on the message row of the panel, one after another,
+//! with a history for the `:messages` view.
+
+use crate::draw;
+use rustodo::date;
+use rustodo::settings::MessageDurations;
+use std::collections::VecDeque;
+use std::time::Duration;
+
+/// Number of messages kept for the `:messages` view.
+const HISTORY_SIZE: usize = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Info,
+    Success,
+    Warning,
+    Error,
+}
+
+impl Severity {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Severity::Info => "info",
+            Severity::Success => "success",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        }
+    }
+
+    pub fn style(&self, text: String) -> String {
+        match self {
+            Severity::Info => text,
+            Severity::Success => draw::success(text),
+            Severity::Warning => draw::warning(text),
+            Severity::Error => draw::danger(text),
+        }
+    }
+}
+
+/// How long messages of a severity are shown, `None` if they stay until
+/// they are dismissed.
+pub fn duration(durations: &MessageDurations, severity: Severity) -> Option<Duration> {
+    let millis = match severity {
+        Severity::Info => durations.info,
+        Severity::Success => durations.success,
+        Severity::Warning => durations.warning,
+        Severity::Error => durations.error,
+    };
+
+    match millis {
+        0 => None,
+        millis => Some(Duration::from_millis(millis)),
+    }
+}
+
+#[derive(Clone)]
+pub struct Message {
+    pub id: u64,
+    pub severity: Severity,
+    pub text: String,
+    pub created_at: u64,
+}
+
+#[derive(Default)]
+pub struct Messages {
+    current: Option<Message>,
+    queue: VecDeque<Message>,
+    history: VecDeque<Message>,
+    next_id: u64,
+}
+
+impl Messages {
+    /// Adds a message, returns true if it is shown right away because no
+    /// other message is.
+    pub fn push(&mut self, severity: Severity, text: String) -> bool {
+        self.next_id += 1;
+        let message = Message {
+            id: self.next_id,
+            severity,
+            text,
+            created_at: date::now(),
+        };
+
+        if self.history.len() == HISTORY_SIZE {
+            self.history.pop_front();
+        }
+        self.history.push_back(message.clone());
+
+        if self.current.is_none() {
+            self.current = Some(message);
+            true
+        } else {
+            self.queue.push_back(message);
+            false
+        }
+    }
+
+    pub fn current(&self) -> Option<&Message> {
+        self.current.as_ref()
+    }
+
+    /// Number of messages waiting behind the current one.
+    pub fn pending(&self) -> usize {
+        self.queue.len()
+    }
+
+    /// Replaces the current message with the next one in the queue.
+    pub fn advance(&mut self) -> Option<&Message> {
+        self.current = self.queue.pop_front();
+        self.current.as_ref()
+    }
+
+    /// Advances if the message with the given id is still shown, returns
+    /// false if it was dismissed already.
+    pub fn expire(&mut self, id: u64) -> bool {
+        match &self.current {
+            Some(message) if message.id == id => {
+                self.advance();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Past messages, oldest first.
+    pub fn history(&self) -> impl Iterator<Item = &Message> {
+        self.history.iter()
+    }
+}
@@ -1,29 +1,83 @@
+use crate::backend::{Backend, Headless, Tty};
 use crate::draw::{self, position, warning};
-use crate::reader::Reader;
-pub use crate::todo::{Todo, TodoList};
-pub use crate::Settings;
-
-use std::io::{stdout, Stdout, Write};
-use std::sync::mpsc::{self, Receiver, Sender};
-use std::thread;
-use std::time::Duration;
+use crate::frame::Renderer;
+use crate::input::Input;
+use crate::keymap::{Action, Keymap};
+use crate::messages::{self, Messages, Severity};
+use crate::terminal;
+use crate::timers::{Timer, Timers};
+use rustodo::storage::Storage;
+use rustodo::{archive, date, lists, merge, todo};
+use rustodo::{Settings, Todo, TodoList};
+
+use std::collections::{BTreeSet, HashSet};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::time::{Duration, Instant};
+use std::{mem, thread};
 use termion::event::Key;
-use termion::raw::{IntoRawMode, RawTerminal};
-use termion::terminal_size;
 
-#[derive(PartialEq, Eq)]
-pub enum Operation {
+/// Terminal row of the first todo, below the top border.
+const CONTENT_TOP: u16 = 2;
+/// How often the stored list is checked for changes made elsewhere.
+const WATCH_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Operations that need text typed for them.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Operation {
     Create,
     Update,
-    Delete,
+    Tag,
+    Priority,
+    MoveTo,
+    CopyTo,
+}
+
+/// What keys do, see `handle_key`. Every mode but `Normal` draws its prompt
+/// above the message row.
+#[derive(Clone, PartialEq, Eq)]
+enum Mode {
+    /// Keys are looked up in the key map.
+    Normal,
+    /// Text for an operation is typed, Enter commits it.
+    Insert(Operation, Input),
+    /// Waits for y or n before deleting the targets.
+    Confirm,
+    /// Highlights the first todo containing the typed text, going back to
+    /// the todo highlighted before on Esc.
+    Search(Input, usize),
+    /// A command is typed after `:`.
+    Command(Input),
+}
+
+impl Mode {
+    /// Name in the status bar, `None` in normal mode, which is named after
+    /// what is shown.
+    fn name(&self) -> Option<&'static str> {
+        match self {
+            Mode::Normal => None,
+            Mode::Insert(..) => Some("INSERT"),
+            Mode::Confirm => Some("CONFIRM"),
+            Mode::Search(..) => Some("SEARCH"),
+            Mode::Command(_) => Some("COMMAND"),
+        }
+    }
 }
 
 #[derive(PartialEq, Eq)]
-pub enum UiSection {
+enum UiSection {
     Status,
     Content,
 }
 
+/// Views shown instead of the list.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Overlay {
+    Help,
+    Messages,
+}
+
 /*
 #[derive(PartialEq, Eq)]
 pub enum FilterType {
@@ -35,66 +89,270 @@ pub enum FilterType {
 #[derive(PartialEq, Eq)]
 pub enum Event {
     Redraw,
-    Clear(Option<UiSection>),
+    /// The terminal was resized.
+    Resize,
     Quit,
     MoveUp,
     MoveDown,
     HighlightUp,
     HighlightDown,
     Toggle,
+    Mark,
+    MarkRange,
+    Click(u16, u16),
+    Drag(u16, u16),
     Save,
-    Filter,
-    Input(Operation),
-    Commit(Operation, String),
+    Archive,
+    ToggleArchive,
+    Restore,
+    Help,
+    /// The stored list may have been changed elsewhere.
+    Reload,
+    /// A signal that ends the process, see `terminal`.
+    Interrupt(i32),
+    Suspend,
+    /// The process was continued after it was stopped.
+    Resume,
+    /// The replayed keys ran out, see `Headless`.
+    Closed,
     KeyPressed(Key),
     IoError(String),
 }
 
-pub struct Panel {
+pub struct Panel<'a> {
     list: TodoList,
+    /// The archive of `list` while it is being browsed instead of the list.
+    archive: Option<TodoList>,
+    overlay: Option<Overlay>,
+    keymap: Keymap,
+    /// The list as it was last loaded or written, to tell if it has changes.
+    saved: TodoList,
+    /// The stored list, while it was changed elsewhere and `list` has
+    /// changes too. Saving would overwrite it, see `Event::Reload`.
+    changed_on_disk: Option<TodoList>,
     highlighted: usize,
-    stdout: RawTerminal<Stdout>,
+    /// Indices of the todos marked for bulk operations.
+    marked: BTreeSet<usize>,
+    /// Start of a range that is being marked with `V`, ending at `highlighted`.
+    mark_anchor: Option<usize>,
+    backend: Box<dyn Backend>,
     settings: Settings,
+    storage: &'a dyn Storage,
+    /// What was drawn since the last render, see `render`.
     buffer: String,
-    event_sender: Sender<Event>,
+    renderer: Renderer,
     event_receiver: Receiver<Event>,
-    suspend_clear_sender: Option<Sender<()>>,
-    reader: Reader,
+    messages: Messages,
+    /// Deadlines the loop wakes up for between events, see `start_loop`.
+    timers: Timers,
+    /// Ids of the due todos that were already reminded of.
+    reminded: HashSet<i16>,
+    mode: Mode,
 }
 
-impl Panel {
-    pub fn new(list: TodoList, settings: Settings) -> Self {
-        let stdout = stdout().into_raw_mode().unwrap();
+impl<'a> Panel<'a> {
+    pub fn new(list: TodoList, settings: Settings, storage: &'a dyn Storage) -> Self {
+        let (event_sender, event_receiver) = mpsc::channel();
+        let backend = Tty::new(event_sender.clone());
+        Panel::with_backend(
+            list,
+            settings,
+            storage,
+            Box::new(backend),
+            event_sender,
+            event_receiver,
+        )
+    }
+
+    /// A panel of the given size that reads `keys` instead of the terminal
+    /// and quits once they are handled, see `replay`.
+    pub fn headless(
+        list: TodoList,
+        settings: Settings,
+        storage: &'a dyn Storage,
+        size: (u16, u16),
+        keys: Vec<Key>,
+    ) -> Self {
         let (event_sender, event_receiver) = mpsc::channel();
-        let reader = Reader::new(event_sender.clone());
+        let backend = Headless::new(size.0, size.1, keys, event_sender.clone());
+        Panel::with_backend(
+            list,
+            settings,
+            storage,
+            Box::new(backend),
+            event_sender,
+            event_receiver,
+        )
+    }
+
+    fn with_backend(
+        list: TodoList,
+        settings: Settings,
+        storage: &'a dyn Storage,
+        backend: Box<dyn Backend>,
+        event_sender: Sender<Event>,
+        event_receiver: Receiver<Event>,
+    ) -> Self {
+        let (w, h) = backend.size();
+        let keymap = Keymap::new(&settings.keybindings)
+            .expect("Key bindings are checked when loading the settings");
+        let saved = list.clone();
+        if let Some(path) = storage.watch_path(&list.name) {
+            Panel::spawn_watcher(path, event_sender.clone());
+        }
         Panel {
             list,
+            archive: None,
+            overlay: None,
+            keymap,
+            saved,
+            changed_on_disk: None,
             highlighted: 0,
-            stdout,
-            reader,
+            marked: BTreeSet::new(),
+            mark_anchor: None,
+            backend,
             settings,
+            storage,
             buffer: String::new(),
-            event_sender,
+            renderer: Renderer::new(w, h),
             event_receiver,
-            suspend_clear_sender: None,
+            messages: Messages::default(),
+            timers: Timers::default(),
+            reminded: HashSet::new(),
+            mode: Mode::Normal,
         }
     }
 
+    /// Polls the file the list is stored in, sending `Event::Reload` when it
+    /// changes. The panel's own saves are sent too, see `reload`.
+    fn spawn_watcher(path: PathBuf, events: Sender<Event>) {
+        let stamp = move || {
+            fs::metadata(&path)
+                .ok()
+                .map(|metadata| (metadata.modified().ok(), metadata.len()))
+        };
+        thread::spawn(move || {
+            let mut last = stamp();
+            loop {
+                thread::sleep(WATCH_INTERVAL);
+                let current = stamp();
+                if current != last {
+                    last = current;
+                    if events.send(Event::Reload).is_err() {
+                        return;
+                    }
+                }
+            }
+        });
+    }
+
     pub fn start(&mut self) {
+        self.set_mouse(true);
         self.clear(None, false);
         self.redraw();
         self.start_loop();
     }
 
+    fn start_timers(&mut self) {
+        if self.settings.autosave > 0 {
+            let interval = Duration::from_secs(self.settings.autosave);
+            self.timers.set(Timer::Autosave, interval);
+        }
+        self.set_tick();
+        self.remind();
+    }
+
+    /// Ticks at the start of the next minute, when the clock changes.
+    fn set_tick(&mut self) {
+        let seconds = 60 - date::now() % 60;
+        self.timers.set(Timer::Tick, Duration::from_secs(seconds));
+    }
+
+    fn fire(&mut self, timer: Timer) {
+        match timer {
+            Timer::Expire(id) => {
+                if self.messages.expire(id) {
+                    self.show_message();
+                }
+            }
+            Timer::Autosave => {
+                self.autosave();
+                let interval = Duration::from_secs(self.settings.autosave);
+                self.timers.set(Timer::Autosave, interval);
+            }
+            Timer::Tick => {
+                self.set_tick();
+                self.remind();
+                if self.settings.clock {
+                    self.redraw();
+                }
+            }
+        }
+    }
+
+    /// Saves changes without a message, unless saving would overwrite
+    /// changes made elsewhere.
+    fn autosave(&mut self) {
+        if self.is_dirty() && self.changed_on_disk.is_none() {
+            self.write(false);
+        }
+    }
+
+    /// Warns about open todos that became due, once per todo.
+    fn remind(&mut self) {
+        if !self.settings.reminders {
+            return;
+        }
+        let now = date::now();
+        let due: Vec<&Todo> = self
+            .list
+            .todos
+            .iter()
+            .filter(|todo| !todo.done && matches!(todo.due_at, Some(due_at) if due_at <= now))
+            .filter(|todo| self.reminded.insert(todo.id))
+            .collect();
+        let text = match due.as_slice() {
+            [] => return,
+            [todo] => format!("'{}' is due", todo.item),
+            todos => format!("{} todos are due", todos.len()),
+        };
+        self.notify(Severity::Warning, text);
+    }
+
+    /// Takes the terminal back after the process was continued.
+    fn resume(&mut self) {
+        self.backend.enter();
+        self.renderer.invalidate();
+        self.set_mouse(true);
+        self.clear(None, false);
+        self.redraw();
+    }
+
     pub fn quit(&mut self) {
-        self.push(draw::clear_all());
-        self.push(draw::show_cursor());
+        self.set_mouse(false);
         self.render();
+        let out = format!("{}{}", draw::show_cursor(), draw::main_screen());
+        self.backend.write(&out);
+    }
+
+    /// The text on screen, without styles.
+    pub fn screen(&self) -> String {
+        self.renderer.frame().text()
+    }
+
+    pub fn list(&self) -> &TodoList {
+        &self.list
     }
 
+    /// Draws what was pushed into the frame, writing only the cells that
+    /// changed.
     fn render(&mut self) {
-        self.stdout.write_all(self.buffer.as_bytes()).unwrap();
-        self.stdout.flush().unwrap();
+        let (w, h) = self.backend.size();
+        self.renderer.resize(w, h);
+        let buffer = mem::take(&mut self.buffer);
+        self.renderer.draw(&buffer);
+        let out = self.renderer.flush();
+        self.backend.write(&out);
     }
 
     fn push(&mut self, text: String) {
@@ -105,13 +363,16 @@ impl Panel {
         self.clear(Some(UiSection::Content), false);
         let content = self.draw_content();
         self.push(content);
+        self.draw_status_bar();
+        self.draw_prompt();
         self.render();
     }
 
     fn clear(&mut self, section: Option<UiSection>, render: bool) {
+        let content = matches!(section, Some(UiSection::Content));
         self.push(match section {
             Some(s) => {
-                let (w, h) = terminal_size().unwrap();
+                let (w, h) = self.backend.size();
                 match s {
                     UiSection::Content => draw::clear_before(w, h - 5),
                     UiSection::Status => draw::clear_after(w, h - 5),
@@ -120,22 +381,34 @@ impl Panel {
             None => draw::clear_all(),
         });
         self.push(draw::hide_cursor());
+        if !content {
+            self.draw_status_bar();
+            self.draw_message();
+            self.draw_prompt();
+        }
         if render {
             self.render();
         }
-        self.suspend_clear_sender = None;
+    }
+
+    /// The list currently on screen, which is either the list or its archive.
+    fn visible(&self) -> &TodoList {
+        self.archive.as_ref().unwrap_or(&self.list)
     }
 
     fn draw_todos(&mut self) -> (String, usize, usize) {
         let mut out = String::new();
         let mut completed = 0;
-        for i in 0..self.list.todos.len() {
-            let todo = &self.list.todos[i];
+        let todos = &self.visible().todos;
+        for (i, todo) in todos.iter().enumerate() {
             if todo.done {
                 completed += 1;
             }
 
-            out.push_str(self.draw_todo(todo, i == self.highlighted).as_str());
+            out.push_str(
+                self.draw_todo(todo, i == self.highlighted, self.is_marked(i))
+                    .as_str(),
+            );
 
             /*if let Some(filter) = &self.filter {
                 match filter {
@@ -154,20 +427,249 @@ impl Panel {
                 out.push_str(self.draw_todo(todo, i == self.highlighted).as_str());
                 }*/
         }
-        (out, completed, self.list.todos.len())
+        (out, completed, todos.len())
     }
 
     fn draw_content(&mut self) -> String {
+        match self.overlay {
+            Some(Overlay::Help) => return self.draw_help(),
+            Some(Overlay::Messages) => return self.draw_history(),
+            None => {}
+        }
+
         let (mut out, completed, total) = self.draw_todos();
         if out.is_empty() {
             out.push_str("Nothing to display...");
         }
-        let (w, _) = terminal_size().unwrap();
-        let title_bottom = format!("{}/{}", completed, total);
-        draw::bordered(out, self.list.name.clone(), title_bottom, w)
+        let (w, _) = self.backend.size();
+        let mut title_bottom = format!("{}/{}", completed, total);
+        let marked = self.targets().len();
+        if self.archive.is_none() && (!self.marked.is_empty() || self.mark_anchor.is_some()) {
+            title_bottom.push_str(format!(" ({} marked)", marked).as_str());
+        }
+        let title = match self.archive {
+            Some(_) => format!("{} (archive)", self.list.name),
+            None => self.list.name.clone(),
+        };
+        draw::bordered(out, title, title_bottom, w)
+    }
+
+    /// Every action with the keys currently bound to it.
+    fn draw_help(&self) -> String {
+        let rows: Vec<Vec<String>> = Action::ALL
+            .iter()
+            .map(|action| vec![self.keymap.keys(*action), action.description().to_string()])
+            .collect();
+        let (w, _) = self.backend.size();
+        let close = format!("{} to close", self.keymap.keys(Action::Help));
+        draw::bordered(draw::table(&rows, &[]), "Help".into(), close, w)
+    }
+
+    /// Past messages, oldest first.
+    fn draw_history(&self) -> String {
+        let rows: Vec<Vec<String>> = self
+            .messages
+            .history()
+            .map(|message| {
+                vec![
+                    date::format_datetime(message.created_at),
+                    message.severity.style(message.severity.name().to_string()),
+                    message.text.clone(),
+                ]
+            })
+            .collect();
+        let out = match rows.len() {
+            0 => String::from("No messages"),
+            _ => draw::table(&rows, &[]),
+        };
+        let (w, _) = self.backend.size();
+        draw::bordered(
+            out,
+            "Messages".into(),
+            format!("{} messages", rows.len()),
+            w,
+        )
+    }
+
+    /// Mode, unsaved changes and the most common keys on the last row.
+    fn draw_status_bar(&mut self) {
+        if !self.settings.status_bar {
+            return;
+        }
+
+        let mode = if let Some(name) = self.mode.name() {
+            name
+        } else if self.overlay == Some(Overlay::Help) {
+            "HELP"
+        } else if self.overlay == Some(Overlay::Messages) {
+            "MESSAGES"
+        } else if self.archive.is_some() {
+            "ARCHIVE"
+        } else if !self.marked.is_empty() || self.mark_anchor.is_some() {
+            "SELECT"
+        } else {
+            "NORMAL"
+        };
+        let mut left = format!("{}  {}", mode, self.list.name);
+        if self.is_dirty() {
+            left.push_str(" [+]");
+        }
+        if self.changed_on_disk.is_some() {
+            left.push_str(" [changed on disk]");
+        }
+
+        let mut hints: Vec<String> = [Action::Help, Action::Create, Action::Save, Action::Quit]
+            .iter()
+            .map(|action| format!("{} {}", self.keymap.keys(*action), action.name()))
+            .collect();
+        if self.settings.clock {
+            hints.push(date::format_time(date::now()));
+        }
+
+        let (w, h) = self.backend.size();
+        let bar = draw::status_bar(left, hints.join("  "), w);
+        self.push(position(bar, 1, h));
+    }
+
+    /// Row of flashes and confirmations, above the status bar if it is shown.
+    fn message_row(&self) -> u16 {
+        let (_, h) = self.backend.size();
+        if self.settings.status_bar {
+            h - 1
+        } else {
+            h
+        }
+    }
+
+    fn is_dirty(&self) -> bool {
+        self.list.serialize() != self.saved.serialize()
+    }
+
+    fn mark_saved(&mut self) {
+        self.saved = self.list.clone();
+    }
+
+    fn save(&mut self, force: bool) {
+        if self.write(force) {
+            self.notify(Severity::Success, "Successfully saved list".into());
+        }
+    }
+
+    /// Saves the list, unless it was changed elsewhere since it was loaded
+    /// and `force` isn't set. Returns whether it was saved.
+    fn write(&mut self, force: bool) -> bool {
+        if !force && self.changed_elsewhere() {
+            return false;
+        }
+
+        match self.storage.save(&self.list) {
+            Ok(_) => {
+                self.mark_saved();
+                self.changed_on_disk = None;
+                self.redraw();
+                true
+            }
+            Err(err) => {
+                self.notify(Severity::Error, format!("Unable to save list: {}", err));
+                false
+            }
+        }
+    }
+
+    /// Whether the stored list was changed elsewhere since it was loaded, in
+    /// which case the changes are offered to merge.
+    fn changed_elsewhere(&mut self) -> bool {
+        match self.storage.load(&self.list.name) {
+            Ok(stored) if stored.serialize() != self.saved.serialize() => {
+                self.changed_on_disk = Some(stored);
+                self.redraw();
+                self.notify_changed_on_disk();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Takes in the stored list if it was changed elsewhere. Without changes
+    /// of our own it replaces the list, otherwise it waits for `:merge` or
+    /// `:reload`.
+    fn reload(&mut self) {
+        let stored = match self.storage.load(&self.list.name) {
+            Ok(stored) => stored,
+            Err(err) => {
+                let text = format!("Unable to reload '{}': {}", self.list.name, err);
+                return self.notify(Severity::Warning, text);
+            }
+        };
+        // Also the case after our own saves
+        if stored.serialize() == self.saved.serialize() {
+            return;
+        }
+
+        if self.is_dirty() {
+            self.changed_on_disk = Some(stored);
+            self.redraw();
+            self.notify_changed_on_disk();
+        } else {
+            self.replace_list(stored);
+            let text = format!("Reloaded '{}', it was changed on disk", self.list.name);
+            self.notify(Severity::Info, text);
+        }
+    }
+
+    fn notify_changed_on_disk(&mut self) {
+        let text = format!(
+            "'{}' changed on disk, use :merge, :reload or :save! to overwrite",
+            self.list.name
+        );
+        self.notify(Severity::Warning, text);
+    }
+
+    /// Replaces the list with the stored one, dropping changes.
+    fn replace_list(&mut self, stored: TodoList) {
+        self.list = stored;
+        self.mark_saved();
+        self.changed_on_disk = None;
+        self.clear_marks();
+        self.highlighted = self
+            .highlighted
+            .min(self.visible().total().saturating_sub(1));
+        self.redraw();
+    }
+
+    /// Merges the changes made elsewhere into the list, which then has the
+    /// merged changes to save.
+    fn merge(&mut self) {
+        let stored = match self.changed_on_disk.take() {
+            Some(stored) => stored,
+            None => return self.notify(Severity::Info, "Nothing to merge".into()),
+        };
+
+        let (merged, conflicts) = merge::merge(&self.saved, &self.list, &stored);
+        self.list = merged;
+        self.saved = stored;
+        self.clear_marks();
+        self.highlighted = self
+            .highlighted
+            .min(self.visible().total().saturating_sub(1));
+        self.redraw();
+
+        match conflicts {
+            0 => self.notify(
+                Severity::Success,
+                "Merged the changes on disk, save to keep them".into(),
+            ),
+            count => self.notify(
+                Severity::Warning,
+                format!(
+                    "Merged the changes on disk, check the {} todos that were changed on both sides",
+                    count
+                ),
+            ),
+        }
     }
 
-    fn draw_todo(&self, todo: &Todo, highlight: bool) -> String {
+    fn draw_todo(&self, todo: &Todo, highlight: bool, marked: bool) -> String {
         let mut out = String::new();
 
         if todo.done {
@@ -177,9 +679,13 @@ impl Panel {
         }
 
         out.push(' ');
-        out.push_str(&todo.item.as_str());
+        out.push_str(todo.item.as_str());
         out.push('\n');
 
+        if marked {
+            out = draw::invert(out);
+        }
+
         if highlight {
             draw::bold(out)
         } else {
@@ -187,127 +693,750 @@ impl Panel {
         }
     }
 
+    /// Draws the prompt of the current mode, leaving the cursor in its
+    /// input.
+    fn draw_prompt(&mut self) {
+        let mode = self.mode.clone();
+        match &mode {
+            Mode::Normal => {}
+            Mode::Insert(operation, input) => {
+                let title = match operation {
+                    Operation::Create => String::from("Todo"),
+                    Operation::Update => self
+                        .list
+                        .todos
+                        .get(self.highlighted)
+                        .map(|todo| todo.item.clone())
+                        .unwrap_or_default(),
+                    Operation::Tag => String::from("Tags (-tag removes)"),
+                    Operation::Priority => String::from("Priority (A-Z, empty clears)"),
+                    Operation::MoveTo => self.list_prompt("Move to"),
+                    Operation::CopyTo => self.list_prompt("Copy to"),
+                };
+                self.draw_input(title, input);
+            }
+            Mode::Confirm => self.draw_confirm(),
+            Mode::Search(input, _) => self.draw_input(String::from("Search"), input),
+            Mode::Command(input) => self.draw_input(String::from(":"), input),
+        }
+    }
+
     fn draw_confirm(&mut self) {
-        self.suspend_clear();
+        let question = match self.targets().len() {
+            0 | 1 => String::from("Are you sure? (y/n)"),
+            count => format!("Delete {} todos? (y/n)", count),
+        };
+        let row = self.message_row() - 1;
+        self.push(position(warning(question), 1, row));
+    }
 
-        let (_, h) = terminal_size().unwrap();
-        self.push(position(warning("Are you sure? (y/n)".into()), 1, h));
-        self.render();
+    /// The input box, just above the message row so messages stay visible.
+    fn draw_input(&mut self, name: String, input: &Input) {
+        let (w, _) = self.backend.size();
+        let row = self.message_row() - 3;
+
+        self.push(draw::input(name.as_str(), 1, row, w));
+        let (text, column) = input.view(w as usize - 3);
+        self.push(text);
+        self.push(position(String::new(), 2 + column as u16, row + 1));
     }
 
-    fn draw_input(&mut self, name: String) {
-        self.suspend_clear();
+    fn set_mouse(&mut self, enabled: bool) {
+        if self.settings.mouse {
+            self.push(if enabled {
+                draw::enable_mouse()
+            } else {
+                draw::disable_mouse()
+            });
+        }
+    }
 
-        self.stdout.suspend_raw_mode().unwrap();
+    /// Shows a message once the ones before it are gone.
+    fn notify(&mut self, severity: Severity, text: String) {
+        if self.messages.push(severity, text) {
+            self.show_message();
+        } else {
+            // Updates the count of waiting messages
+            self.clear(Some(UiSection::Status), true);
+        }
+    }
 
-        let (w, h) = terminal_size().unwrap();
+    /// Starts the timer of the current message and draws it.
+    fn show_message(&mut self) {
+        if let Some(message) = self.messages.current() {
+            if let Some(duration) =
+                messages::duration(&self.settings.message_durations, message.severity)
+            {
+                self.timers.set(Timer::Expire(message.id), duration);
+            }
+        }
+        self.clear(Some(UiSection::Status), true);
+    }
 
-        self.push(draw::input(name.as_str(), 1, h - 2, w));
-        self.render();
+    fn draw_message(&mut self) {
+        let message = match self.messages.current() {
+            Some(message) => message,
+            None => return,
+        };
+        let mut text = message.text.clone();
+        if self.messages.pending() > 0 {
+            text.push_str(format!(" (+{} more)", self.messages.pending()).as_str());
+        }
+        let out = message.severity.style(text);
+        let row = self.message_row();
+        self.push(position(out, 1, row));
     }
 
-    fn suspend_clear(&mut self) {
-        if let Some(sender) = &self.suspend_clear_sender {
-            sender.send(()).unwrap()
+    /// Dismisses the current message if it stays until it is dismissed.
+    fn dismiss_message(&mut self) -> bool {
+        match self.messages.current() {
+            Some(message)
+                if messages::duration(&self.settings.message_durations, message.severity)
+                    .is_none() =>
+            {
+                self.messages.advance();
+                self.show_message();
+                true
+            }
+            _ => false,
         }
     }
 
-    fn draw_flash(&mut self, out: String) {
-        self.suspend_clear();
+    fn delete_todos(&mut self) {
+        let targets = self.targets();
+        for i in targets.iter().rev() {
+            self.list.todos.remove(*i);
+        }
 
-        let (_, h) = terminal_size().unwrap();
-        let (cancel_sender, cancel_receiver) = mpsc::channel();
-        let sender = self.event_sender.clone();
+        let removed_before = targets.iter().filter(|i| **i < self.highlighted).count();
+        self.highlighted -= removed_before;
+        self.highlighted = self.highlighted.min(self.list.total().saturating_sub(1));
+        self.clear_marks();
+    }
 
-        self.suspend_clear_sender = Some(cancel_sender);
+    fn update_todo(&mut self, item: String) {
+        self.list.todos[self.highlighted].set_item(item);
+    }
 
-        self.push(draw::position(out, 1, h));
-        self.render();
+    /// Marks every target as done, or unchecks them all if they already are.
+    fn toggle_todos(&mut self) {
+        let targets = self.targets();
+        let done = targets.iter().any(|i| !self.list.todos[*i].done);
+        for i in targets {
+            if self.list.todos[i].done != done {
+                self.list.todos[i].toggle();
+            }
+        }
+    }
 
-        thread::spawn(move || {
-            thread::sleep(Duration::from_secs(1));
-            if cancel_receiver.try_recv().is_err() {
-                sender.send(Event::Clear(Some(UiSection::Status))).unwrap();
+    /// Adds the given tags to every target, tags prefixed with `-` are removed.
+    fn tag_todos(&mut self, tags: String) {
+        for i in self.targets() {
+            for tag in tags.split_whitespace() {
+                match tag.strip_prefix('-') {
+                    Some(tag) => self.list.todos[i].remove_tag(tag),
+                    None => self.list.todos[i].add_tag(tag),
+                }
             }
-        });
+        }
     }
 
-    fn delete_todo(&mut self) {
-        self.list.todos.remove(self.highlighted);
-        if self.list.todos.len() == 0 {
-            self.highlighted = 0;
-        } else if self.highlighted == self.list.todos.len() {
-            self.highlighted -= 1;
+    fn prioritize_todos(&mut self, priority: String) {
+        let priority = match todo::parse_priority(&priority) {
+            Some(priority) => priority,
+            None => {
+                return self.notify(
+                    Severity::Error,
+                    format!("Invalid priority '{}', use A-Z or 1-26", priority.trim()),
+                )
+            }
+        };
+        for i in self.targets() {
+            self.list.todos[i].set_priority(priority);
         }
     }
 
-    fn update_todo(&mut self, item: String) {
-        self.list.todos[self.highlighted].item = item;
+    /// Indices the next operation applies to: the marked todos, or the
+    /// highlighted one when nothing is marked.
+    fn targets(&self) -> Vec<usize> {
+        let mut targets = self.marked.clone();
+        if let Some(anchor) = self.mark_anchor {
+            targets.extend(anchor.min(self.highlighted)..=anchor.max(self.highlighted));
+        }
+        if targets.is_empty() && !self.list.todos.is_empty() {
+            targets.insert(self.highlighted);
+        }
+        targets.into_iter().collect()
+    }
+
+    fn is_marked(&self, index: usize) -> bool {
+        if self.archive.is_some() {
+            return false;
+        }
+        match self.mark_anchor {
+            Some(anchor)
+                if (anchor.min(self.highlighted)..=anchor.max(self.highlighted))
+                    .contains(&index) =>
+            {
+                true
+            }
+            _ => self.marked.contains(&index),
+        }
+    }
+
+    fn mark(&mut self) {
+        if !self.list.todos.is_empty() && !self.marked.remove(&self.highlighted) {
+            self.marked.insert(self.highlighted);
+        }
+    }
+
+    /// Starts a range at the highlighted todo, or marks the range that was started.
+    fn mark_range(&mut self) {
+        match self.mark_anchor {
+            Some(_) => self.fix_mark_range(),
+            None if !self.list.todos.is_empty() => self.mark_anchor = Some(self.highlighted),
+            None => {}
+        }
+    }
+
+    fn fix_mark_range(&mut self) {
+        if self.mark_anchor.is_some() {
+            self.marked = self.targets().into_iter().collect();
+            self.mark_anchor = None;
+        }
+    }
+
+    fn clear_marks(&mut self) {
+        self.marked.clear();
+        self.mark_anchor = None;
+    }
+
+    /// Moves or copies the targets into another list and saves it. A move
+    /// also saves their removal from this list, other changes to the list
+    /// stay unsaved.
+    fn transfer_todos(&mut self, target: String, remove: bool) {
+        if self.list.todos.is_empty() || target.trim().is_empty() {
+            return;
+        }
+
+        let name = match self
+            .storage
+            .names()
+            .and_then(|names| lists::complete(&names, &target))
+        {
+            Ok(name) if name == self.list.name => {
+                return self.notify(Severity::Warning, "Todos are already in this list".into())
+            }
+            Ok(name) => name,
+            Err(err) => return self.notify(Severity::Error, err.to_string()),
+        };
+
+        let mut target = match self.storage.load(&name) {
+            Ok(list) => list,
+            Err(err) => return self.notify(Severity::Error, err.to_string()),
+        };
+        if remove && self.changed_elsewhere() {
+            return;
+        }
+
+        let mut todos: Vec<Todo> = self
+            .targets()
+            .into_iter()
+            .map(|i| self.list.todos[i].clone())
+            .collect();
+        let count = todos.len();
+        let ids: Vec<i16> = todos.iter().map(|todo| todo.id).collect();
+        if !remove {
+            // A copy is a new calendar entry
+            todos.iter_mut().for_each(|todo| todo.uid = None);
+        }
+        target.append(todos);
+
+        // The target is written first, so a failure can't lose any todos
+        if let Err(err) = self.storage.save(&target) {
+            return self.notify(
+                Severity::Error,
+                format!("Unable to save '{}': {}", target.name, err),
+            );
+        }
+
+        // Only the moved todos are saved, other changes stay unsaved
+        if remove {
+            let mut saved = self.saved.clone();
+            saved.todos.retain(|todo| !ids.contains(&todo.id));
+            if let Err(err) = self.storage.save(&saved) {
+                self.redraw();
+                return self.notify(Severity::Error, format!("Unable to save list: {}", err));
+            }
+            self.saved = saved;
+            self.delete_todos();
+        } else {
+            self.clear_marks();
+        }
+
+        self.redraw();
+        let verb = if remove { "Moved" } else { "Copied" };
+        self.notify(
+            Severity::Success,
+            format!("{} {} todos to '{}'", verb, count, target.name),
+        );
+    }
+
+    /// Title of the list name prompt, showing the lists that can be completed.
+    fn list_prompt(&self, title: &str) -> String {
+        let (w, _) = self.backend.size();
+        let names = self.storage.names().unwrap_or_default();
+        let others: Vec<String> = names.into_iter().filter(|n| *n != self.list.name).collect();
+
+        let mut prompt = format!("{} ({})", title, others.join(", "));
+        let max = w.saturating_sub(6) as usize;
+        if prompt.chars().count() > max {
+            prompt = prompt.chars().take(max.saturating_sub(4)).collect();
+            prompt.push_str("...)");
+        }
+        prompt
     }
 
     fn create_todo(&mut self, item: String) {
-        self.list.todos.push(Todo {
-            id: 2,
-            item,
-            priority: 0,
-            tags: vec![],
-            done: false,
-        });
+        let todo = Todo::new(self.list.next_id(), item);
+        self.list.todos.push(todo);
     }
 
-    fn move_down(&mut self) {
-        let tmp = self.list.todos[self.highlighted].clone();
-        self.list.todos[self.highlighted] = self.list.todos[self.highlighted + 1].clone();
-        self.list.todos[self.highlighted + 1] = tmp;
-        self.highlighted += 1;
+    /// Archives the completed todos. Only their removal is saved, other
+    /// changes to the list stay unsaved.
+    fn archive_completed(&mut self) {
+        let completed: Vec<Todo> = self.list.todos.iter().filter(|t| t.done).cloned().collect();
+        if completed.is_empty() {
+            return self.notify(Severity::Info, "No completed todos to archive".into());
+        }
+        if self.changed_elsewhere() {
+            return;
+        }
+
+        let mut saved = self.saved.clone();
+        match archive::archive(&mut saved, completed, self.storage) {
+            Ok(count) => {
+                self.saved = saved;
+                self.list.todos.retain(|todo| !todo.done);
+                self.clear_marks();
+                self.highlighted = self.highlighted.min(self.list.total().saturating_sub(1));
+                self.redraw();
+                self.notify(Severity::Success, format!("Archived {} todos", count));
+            }
+            Err(err) => self.notify(Severity::Error, format!("Unable to archive: {}", err)),
+        }
+    }
+
+    fn toggle_archive(&mut self) {
+        if self.archive.take().is_none() {
+            match self.storage.load_archive(&self.list.name) {
+                Ok(archive) => self.archive = Some(archive),
+                Err(err) => return self.notify(Severity::Error, err.to_string()),
+            }
+        }
+        self.highlighted = 0;
+        self.clear_marks();
+        self.clear(None, false);
+        self.redraw();
     }
 
-    fn move_up(&mut self) {
-        let tmp = self.list.todos[self.highlighted].clone();
-        self.list.todos[self.highlighted] = self.list.todos[self.highlighted - 1].clone();
-        self.list.todos[self.highlighted - 1] = tmp;
-        self.highlighted -= 1;
+    fn run_command(&mut self, command: String) {
+        match command.trim() {
+            "" => {}
+            "messages" => self.toggle_overlay(Overlay::Messages),
+            "help" => self.toggle_overlay(Overlay::Help),
+            "merge" => self.merge(),
+            "reload" => match self.storage.load(&self.list.name) {
+                Ok(stored) => {
+                    self.replace_list(stored);
+                    self.notify(Severity::Success, format!("Reloaded '{}'", self.list.name));
+                }
+                Err(err) => self.notify(Severity::Error, format!("Unable to reload: {}", err)),
+            },
+            "save!" => self.save(true),
+            other => self.notify(Severity::Error, format!("Unknown command ':{}'", other)),
+        }
     }
 
-    fn start_loop(&mut self) {
-        self.reader.listen_events();
-        self.handle_next_event();
+    /// Shows the overlay, or the list again if it is already shown.
+    fn toggle_overlay(&mut self, overlay: Overlay) {
+        self.overlay = match self.overlay {
+            Some(shown) if shown == overlay => None,
+            _ => Some(overlay),
+        };
+        self.clear(None, false);
+        self.redraw();
     }
 
-    fn handle_next_event(&mut self) {
-        let event = self.event_receiver.recv().unwrap();
+    /// Whether nothing can be edited, only actions that are read only work.
+    fn is_read_only(&self) -> bool {
+        self.archive.is_some() || self.overlay.is_some()
+    }
 
-        match event {
-            Event::Redraw => self.redraw(),
-            Event::Quit => return self.quit(),
-            Event::Input(op) => match op {
-                Operation::Create => self.draw_input("Todo".into()),
-                Operation::Update => {
-                    if self.list.todos.len() > 0 {
-                        self.draw_input(self.list.todos[self.highlighted].item.clone())
+    /// Moves the highlighted archived todo back into the list. Only the
+    /// restored todo is saved, other changes to the list stay unsaved.
+    fn restore_todo(&mut self) {
+        if self
+            .archive
+            .as_ref()
+            .is_none_or(|archive| archive.todos.is_empty())
+        {
+            return;
+        }
+        if self.changed_elsewhere() {
+            return;
+        }
+        let Some(archive) = self.archive.as_mut() else {
+            return;
+        };
+
+        let mut saved = self.saved.clone();
+        let restored = archive::restore(&mut saved, archive, self.highlighted, self.storage);
+        self.highlighted = self.highlighted.min(archive.total().saturating_sub(1));
+        if restored.is_ok() {
+            if let Some(mut todo) = saved.todos.last().cloned() {
+                // The id may be taken by a todo that isn't saved yet
+                if self.list.todos.iter().any(|t| t.id == todo.id) {
+                    todo.id = self.list.next_id();
+                }
+                self.list.todos.push(todo);
+            }
+            self.saved = saved;
+        }
+        self.redraw();
+
+        match restored {
+            Ok(_) => self.notify(Severity::Success, "Restored todo".into()),
+            Err(err) => self.notify(Severity::Error, format!("Unable to restore: {}", err)),
+        }
+    }
+
+    /// Index of the todo drawn at the given (one-based) terminal row.
+    fn todo_at(&self, y: u16) -> Option<usize> {
+        let index = y.checked_sub(CONTENT_TOP)? as usize;
+        if index < self.visible().total() {
+            Some(index)
+        } else {
+            None
+        }
+    }
+
+    /// Highlights the clicked todo, a click on its checkbox also toggles it.
+    fn click(&mut self, x: u16, y: u16) {
+        let index = match self.todo_at(y) {
+            Some(index) => index,
+            None => return,
+        };
+        self.highlighted = index;
+
+        if self.archive.is_none() {
+            let todo = &mut self.list.todos[index];
+            let symbol = if todo.done {
+                &self.settings.checked_symbol
+            } else {
+                &self.settings.unchecked_symbol
+            };
+            if (x as usize) <= symbol.chars().count() {
+                todo.toggle();
+            }
+        }
+        self.redraw();
+    }
+
+    /// Moves the highlighted todo (or the marked ones it belongs to) to the
+    /// row the mouse was dragged to.
+    fn drag(&mut self, y: u16) {
+        if self.archive.is_some() || self.list.todos.is_empty() {
+            return;
+        }
+        let target = match self.todo_at(y) {
+            Some(index) => index,
+            None if y < CONTENT_TOP => 0,
+            None => self.list.total() - 1,
+        };
+        if !self.is_marked(self.highlighted) {
+            self.clear_marks();
+        }
+
+        let start = self.highlighted;
+        while self.highlighted < target && self.move_todos(true) {}
+        while self.highlighted > target && self.move_todos(false) {}
+        if self.highlighted != start {
+            self.redraw();
+        }
+    }
+
+    /// Moves the targets one position down (or up), returns false if one of
+    /// them is already at the edge of the list.
+    fn move_todos(&mut self, down: bool) -> bool {
+        self.fix_mark_range();
+        let targets = self.targets();
+
+        let at_edge = match (targets.first(), targets.last()) {
+            (Some(first), Some(last)) => {
+                if down {
+                    last + 1 >= self.list.total()
+                } else {
+                    *first == 0
+                }
+            }
+            _ => true,
+        };
+        if at_edge {
+            return false;
+        }
+
+        let swaps: Vec<(usize, usize)> = if down {
+            targets.iter().rev().map(|i| (*i, i + 1)).collect()
+        } else {
+            targets.iter().map(|i| (*i, i - 1)).collect()
+        };
+        for (from, to) in swaps {
+            self.list.todos.swap(from, to);
+            if self.highlighted == from {
+                self.highlighted = to;
+            } else if self.highlighted == to {
+                self.highlighted = from;
+            }
+        }
+
+        if !self.marked.is_empty() {
+            self.marked = targets
+                .iter()
+                .map(|i| if down { i + 1 } else { i - 1 })
+                .collect();
+        }
+        true
+    }
+
+    /// Handles a key in the current mode, returns whether the panel keeps
+    /// running.
+    fn handle_key(&mut self, key: Key) -> bool {
+        match mem::replace(&mut self.mode, Mode::Normal) {
+            Mode::Normal => match self.keymap.action(key) {
+                Some(action) if action.is_read_only() || !self.is_read_only() => {
+                    return self.act(action)
+                }
+                _ => {}
+            },
+            Mode::Insert(operation, mut input) => match key {
+                Key::Esc => self.clear(Some(UiSection::Status), true),
+                Key::Char('\n') => self.commit(|panel| match operation {
+                    Operation::Create => panel.create_todo(input.text().into()),
+                    Operation::Update => panel.update_todo(input.text().into()),
+                    Operation::Tag => panel.tag_todos(input.text().into()),
+                    Operation::Priority => panel.prioritize_todos(input.text().into()),
+                    Operation::MoveTo => panel.transfer_todos(input.text().into(), true),
+                    Operation::CopyTo => panel.transfer_todos(input.text().into(), false),
+                }),
+                key => {
+                    let edited = input.edit(key);
+                    self.mode = Mode::Insert(operation, input);
+                    if edited {
+                        self.clear(Some(UiSection::Status), true);
+                    }
+                }
+            },
+            Mode::Confirm => match key {
+                Key::Char('y') => self.commit(|panel| panel.delete_todos()),
+                Key::Char('n') | Key::Esc => self.clear(Some(UiSection::Status), true),
+                _ => self.mode = Mode::Confirm,
+            },
+            Mode::Search(mut input, start) => match key {
+                Key::Char('\n') => self.clear(Some(UiSection::Status), true),
+                Key::Esc => {
+                    self.highlighted = start;
+                    self.clear(Some(UiSection::Status), false);
+                    self.redraw();
+                }
+                key => {
+                    let edited = input.edit(key);
+                    if edited {
+                        self.highlighted = self.search(input.text()).unwrap_or(start);
+                    }
+                    self.mode = Mode::Search(input, start);
+                    if edited {
+                        self.clear(Some(UiSection::Status), false);
+                        self.redraw();
                     }
                 }
-                Operation::Delete => self.draw_confirm(),
             },
-            Event::Commit(op, content) => {
-                self.stdout.activate_raw_mode().unwrap();
-                match op {
-                    Operation::Create => self.create_todo(content),
-                    Operation::Update => self.update_todo(content),
-                    Operation::Delete => self.delete_todo(),
+            Mode::Command(mut input) => match key {
+                Key::Esc => self.clear(Some(UiSection::Status), true),
+                Key::Char('\n') => self.commit(|panel| panel.run_command(input.text().into())),
+                key => {
+                    let edited = input.edit(key);
+                    self.mode = Mode::Command(input);
+                    if edited {
+                        self.clear(Some(UiSection::Status), true);
+                    }
                 }
+            },
+        }
+        true
+    }
+
+    /// Runs the action of a key in normal mode, returns whether the panel
+    /// keeps running.
+    fn act(&mut self, action: Action) -> bool {
+        // Only closing an overlay works while it is shown
+        if self.overlay.is_some() && !matches!(action, Action::Help | Action::Quit | Action::Redraw)
+        {
+            return true;
+        }
+        let event = match action {
+            Action::HighlightUp => Event::HighlightUp,
+            Action::HighlightDown => Event::HighlightDown,
+            Action::Toggle => Event::Toggle,
+            Action::Create => return self.enter(Mode::Insert(Operation::Create, Input::default())),
+            Action::Edit if self.list.todos.is_empty() => return true,
+            Action::Edit => return self.enter(Mode::Insert(Operation::Update, Input::default())),
+            Action::Tag => return self.enter(Mode::Insert(Operation::Tag, Input::default())),
+            Action::Priority => {
+                return self.enter(Mode::Insert(Operation::Priority, Input::default()))
+            }
+            Action::MoveTo => return self.enter(Mode::Insert(Operation::MoveTo, Input::default())),
+            Action::CopyTo => return self.enter(Mode::Insert(Operation::CopyTo, Input::default())),
+            Action::Delete => return self.enter(Mode::Confirm),
+            Action::Mark => Event::Mark,
+            Action::MarkRange => Event::MarkRange,
+            Action::MoveUp => Event::MoveUp,
+            Action::MoveDown => Event::MoveDown,
+            Action::Save => Event::Save,
+            Action::Archive => Event::Archive,
+            Action::ToggleArchive => Event::ToggleArchive,
+            Action::Restore => Event::Restore,
+            Action::Redraw => Event::Redraw,
+            Action::Help => Event::Help,
+            Action::Search => return self.enter(Mode::Search(Input::default(), self.highlighted)),
+            Action::Command => return self.enter(Mode::Command(Input::default())),
+            Action::Quit => Event::Quit,
+        };
+        self.handle_event(event)
+    }
+
+    fn enter(&mut self, mode: Mode) -> bool {
+        self.mode = mode;
+        self.clear(Some(UiSection::Status), true);
+        true
+    }
+
+    /// Leaves the prompt and runs what it was for. The screen is cleared up
+    /// front, so messages of the operation stay visible.
+    fn commit(&mut self, operation: impl FnOnce(&mut Self)) {
+        self.clear(None, false);
+        operation(self);
+        self.redraw();
+    }
+
+    /// Index of the first visible todo containing the text, ignoring case,
+    /// if the text isn't empty.
+    fn search(&self, text: &str) -> Option<usize> {
+        if text.is_empty() {
+            return None;
+        }
+        let text = text.to_lowercase();
+        self.visible()
+            .todos
+            .iter()
+            .position(|todo| todo.item.to_lowercase().contains(&text))
+    }
+
+    /// Handles events until the panel quits, waking up for due timers in
+    /// between.
+    fn start_loop(&mut self) {
+        self.backend.listen();
+        self.start_timers();
+        loop {
+            let received = match self.timers.next() {
+                Some(deadline) => self
+                    .event_receiver
+                    .recv_timeout(deadline.saturating_duration_since(Instant::now())),
+                None => self
+                    .event_receiver
+                    .recv()
+                    .map_err(|_| RecvTimeoutError::Disconnected),
+            };
+            match received {
+                Ok(event) => {
+                    if !self.handle_event(event) {
+                        return;
+                    }
+                }
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => return self.quit(),
+            }
+            for timer in self.timers.due(Instant::now()) {
+                self.fire(timer);
+            }
+        }
+    }
+
+    /// Handles an event, returns whether the panel keeps running.
+    fn handle_event(&mut self, event: Event) -> bool {
+        // Only closing an overlay, and what keeps the screen intact, works
+        // while it is shown
+        if self.overlay.is_some()
+            && !matches!(
+                event,
+                Event::Help
+                    | Event::Quit
+                    | Event::Redraw
+                    | Event::Resize
+                    | Event::Reload
+                    | Event::Interrupt(_)
+                    | Event::Suspend
+                    | Event::Resume
+                    | Event::KeyPressed(_)
+                    | Event::Closed
+                    | Event::IoError(_)
+            )
+        {
+            return true;
+        }
+        // The mouse would change the todos a prompt is for
+        if self.mode != Mode::Normal
+            && matches!(
+                event,
+                Event::Click(..) | Event::Drag(..) | Event::HighlightUp | Event::HighlightDown
+            )
+        {
+            return true;
+        }
+
+        match event {
+            Event::Redraw | Event::Resize => {
+                self.renderer.invalidate();
                 self.clear(None, false);
                 self.redraw();
             }
+            Event::Help => self.toggle_overlay(Overlay::Help),
+            Event::Quit => {
+                if let Some(overlay) = self.overlay {
+                    self.toggle_overlay(overlay);
+                    return true;
+                }
+                if self.dismiss_message() {
+                    return true;
+                }
+                if self.marked.is_empty() && self.mark_anchor.is_none() {
+                    if self.archive.is_some() {
+                        self.toggle_archive();
+                        return true;
+                    }
+                    self.quit();
+                    return false;
+                }
+                self.clear_marks();
+                self.redraw();
+            }
             Event::MoveUp => {
-                if self.list.todos.len() >= 2 && self.highlighted < self.list.todos.len() - 1 {
-                    self.move_down();
+                if self.move_todos(false) {
                     self.redraw();
                 }
             }
             Event::MoveDown => {
-                if self.list.todos.len() >= 2 && self.highlighted > 0 {
-                    self.move_up();
+                if self.move_todos(true) {
                     self.redraw();
                 }
             }
@@ -318,41 +1447,55 @@ impl Panel {
                 }
             }
             Event::HighlightDown => {
-                if self.highlighted < self.list.todos.len() - 1 {
+                if self.highlighted + 1 < self.visible().total() {
                     self.highlighted += 1;
                     self.redraw();
                 }
             }
             Event::Toggle => {
-                if self.list.todos.len() > 0 {
-                    self.list.todos[self.highlighted].toggle();
+                if !self.list.todos.is_empty() {
+                    self.toggle_todos();
                     self.redraw();
                 }
             }
-            Event::Save => {
-                self.list.save(&self.settings.todopath).expect("Error");
-                self.draw_flash(draw::success("Successfully saved list".into()));
+            Event::Click(x, y) => self.click(x, y),
+            Event::Drag(_, y) => self.drag(y),
+            Event::Mark => {
+                self.mark();
+                self.redraw();
             }
-            Event::Filter => {} /*{
-            if self.filter.is_none() {
-            self.filter = Some(FilterType::NonCompleted);
-            } else {
-            self.filter = match self.filter.as_ref().unwrap() {
-            FilterType::NonCompleted => Some(FilterType::Completed),
-            FilterType::Completed => None,
-            };
+            Event::MarkRange => {
+                self.mark_range();
+                self.redraw();
             }
-            self.redraw();
-            }}*/
-            Event::KeyPressed(_) => {}
-            Event::IoError(err) => {
-                self.draw_flash(draw::danger(format!("Unexpected i/o error: {}", err)));
+            Event::Save => self.save(false),
+            Event::Archive => self.archive_completed(),
+            Event::ToggleArchive => self.toggle_archive(),
+            Event::Restore => self.restore_todo(),
+            Event::Reload => self.reload(),
+            Event::Interrupt(signal) => {
+                // Changes would be saved soon anyway, don't lose them
+                if self.settings.autosave > 0 {
+                    self.autosave();
+                }
+                terminal::restore();
+                terminal::exit(signal);
             }
-            Event::Clear(section) => {
-                self.clear(section, true);
+            Event::Suspend => {
+                terminal::restore();
+                terminal::stop();
+                self.resume();
+            }
+            Event::Resume => self.resume(),
+            Event::KeyPressed(key) => return self.handle_key(key),
+            Event::Closed => {
+                self.quit();
+                return false;
+            }
+            Event::IoError(err) => {
+                self.notify(Severity::Error, format!("Unexpected i/o error: {}", err));
             }
         }
-
-        self.handle_next_event();
+        true
     }
 }
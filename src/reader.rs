@@ -1,8 +1,11 @@
-use crate::panel::{Event, Operation, UiSection};
+use crate::panel::Event;
+use crate::terminal::{INTERRUPT_KEY, SUSPEND_KEY};
 use std::{io::stdin, sync::mpsc::Sender, thread};
-use termion::event::Key;
+use termion::event::{Event as TermEvent, Key, MouseButton, MouseEvent};
 use termion::input::TermRead;
 
+/// Forwards keys and mouse events from stdin to the panel, which decides
+/// what they do in its current mode.
 pub struct Reader {
     event_sender: Sender<Event>,
 }
@@ -15,70 +18,25 @@ impl Reader {
     pub fn listen_events(&mut self) {
         let sender = self.event_sender.clone();
         thread::spawn(move || {
-            for k in stdin().keys() {
-                let event = match k {
-                    Ok(key) => match key {
-                        Key::Char('a') => {
-                            sender.send(Event::Input(Operation::Create)).unwrap();
-                            Event::Commit(Operation::Create, Reader::input())
-                        }
-                        Key::Char('q') | Key::Esc => Event::Quit,
-                        Key::Up => Event::HighlightUp,
-                        Key::Down => Event::HighlightDown,
-                        Key::Char('\n') => Event::Toggle,
-                        Key::Char('s') => Event::Save,
-                        Key::Char('e') => {
-                            sender.send(Event::Input(Operation::Update)).unwrap();
-                            Event::Commit(Operation::Update, Reader::input())
-                        }
-                        Key::Char('d') => {
-                            sender.send(Event::Input(Operation::Delete)).unwrap();
-                            if Reader::confirm() {
-                                Event::Commit(Operation::Delete, String::new())
-                            } else {
-                                Event::Clear(Some(UiSection::Status))
-                            }
-                        }
-                        Key::Char('r') => Event::Redraw,
-                        Key::Char('f') => Event::Filter,
-                        Key::Right => Event::MoveUp,
-                        Key::Left => Event::MoveDown,
-                        other => Event::KeyPressed(other),
+            for e in stdin().events() {
+                let event = match e {
+                    Ok(TermEvent::Mouse(mouse)) => match mouse {
+                        MouseEvent::Press(MouseButton::Left, x, y) => Event::Click(x, y),
+                        MouseEvent::Press(MouseButton::WheelUp, _, _) => Event::HighlightUp,
+                        MouseEvent::Press(MouseButton::WheelDown, _, _) => Event::HighlightDown,
+                        MouseEvent::Hold(x, y) => Event::Drag(x, y),
+                        _ => continue,
                     },
+                    Ok(TermEvent::Unsupported(_)) => continue,
+                    Ok(TermEvent::Key(Key::Ctrl(INTERRUPT_KEY))) => Event::Quit,
+                    Ok(TermEvent::Key(Key::Ctrl(SUSPEND_KEY))) => Event::Suspend,
+                    Ok(TermEvent::Key(key)) => Event::KeyPressed(key),
                     Err(err) => Event::IoError(err.to_string()),
                 };
-                sender.send(event).unwrap();
-            }
-        });
-    }
-
-    fn confirm() -> bool {
-        for e in stdin().keys() {
-            if let Some(confirmed) = match e {
-                Ok(k) => match k {
-                    Key::Char('y') => Some(true),
-                    Key::Char('n') => Some(false),
-                    _ => None,
-                },
-                Err(_) => Some(false),
-            } {
-                return confirmed;
-            }
-        }
-        false
-    }
-
-    fn input() -> String {
-        let mut buffer = String::new();
-        for k in stdin().keys() {
-            match k.unwrap() {
-                Key::Char('\n') => break,
-                Key::Char(c) => {
-                    buffer.push(c);
+                if sender.send(event).is_err() {
+                    return;
                 }
-                _ => {}
             }
-        }
-        buffer
+        });
     }
 }
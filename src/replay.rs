@@ -0,0 +1,272 @@
+//! `rustodo --replay <keys-file> <list>`, runs the panel on a headless
+//! screen with keys read from a file and prints the final screen or list.
+//! Changes are kept in memory, the stored lists aren't changed.
+//!
+//! A script gives the same result on every machine: the panel runs with the
+//! default settings, without the clock, reminders or autosave, and the time
+//! todos are created or changed at is always `START`.
+//!
+//! The file holds key names separated by spaces or lines, as they are
+//! written in the `keybindings` settings. Text after `> ` on a line is
+//! typed as it is, and lines starting with `#` are skipped:
+//!
+//! ```text
+//! # add a todo
+//! a
+//! > buy milk
+//! Enter s
+//! ```
+
+use std::collections::HashMap;
+use std::fs;
+use std::sync::Mutex;
+
+use rustodo::storage::Storage;
+use rustodo::{date, Error, Result, Settings, TodoList};
+use termion::event::Key;
+
+use crate::keymap;
+use crate::panel::Panel;
+
+/// Size of the headless screen.
+const WIDTH: u16 = 80;
+const HEIGHT: u16 = 24;
+/// The time of the replay, 2024-01-01 00:00 UTC.
+const START: u64 = 1_704_067_200;
+
+pub enum Dump {
+    Screen,
+    List,
+}
+
+pub struct ReplayOptions {
+    pub keys: String,
+    pub list: String,
+    pub dump: Dump,
+}
+
+pub fn run(settings: Settings, options: ReplayOptions) -> std::result::Result<String, String> {
+    let script = match fs::read_to_string(&options.keys) {
+        Ok(script) => script,
+        Err(err) => return Err(format!("Unable to read '{}': {}", options.keys, err)),
+    };
+    let stored = match rustodo::storage::open(&settings) {
+        Ok(stored) => stored,
+        Err(err) => return Err(err.to_string()),
+    };
+    replay(
+        stored,
+        &settings.todopath,
+        &script,
+        &options.list,
+        options.dump,
+    )
+}
+
+fn replay(
+    stored: Box<dyn Storage>,
+    todopath: &str,
+    script: &str,
+    list: &str,
+    dump: Dump,
+) -> std::result::Result<String, String> {
+    let keys = parse_keys(script)?;
+    let storage = Scratch::new(stored);
+    let list = storage.load(list).map_err(|err| err.to_string())?;
+
+    let mut settings = Settings::with_todopath(todopath.to_string());
+    settings.reminders = false;
+    date::freeze(START);
+
+    let mut panel = Panel::headless(list, settings, &storage, (WIDTH, HEIGHT), keys);
+    panel.start();
+    Ok(match dump {
+        Dump::Screen => panel.screen(),
+        Dump::List => panel.list().serialize(),
+    })
+}
+
+fn parse_keys(script: &str) -> std::result::Result<Vec<Key>, String> {
+    let mut keys = Vec::new();
+    for (number, line) in script.lines().enumerate() {
+        let line = line.trim_start();
+        if line.starts_with('#') {
+            continue;
+        }
+        if let Some(text) = line.strip_prefix("> ") {
+            keys.extend(text.chars().map(Key::Char));
+            continue;
+        }
+        for name in line.split_whitespace() {
+            match keymap::parse_key(name) {
+                Some(key) => keys.push(key),
+                None => return Err(format!("Unknown key '{}' on line {}", name, number + 1)),
+            }
+        }
+    }
+    Ok(keys)
+}
+
+/// The stored lists, with the changes of the replay kept on top of them.
+struct Scratch {
+    stored: Box<dyn Storage>,
+    /// Lists saved by the replay, `None` for lists it removed.
+    lists: Mutex<HashMap<String, Option<TodoList>>>,
+    archives: Mutex<HashMap<String, TodoList>>,
+}
+
+impl Scratch {
+    fn new(stored: Box<dyn Storage>) -> Self {
+        Scratch {
+            stored,
+            lists: Mutex::new(HashMap::new()),
+            archives: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl Storage for Scratch {
+    fn scan(&self) -> Result<(Vec<String>, Vec<String>)> {
+        let (mut names, warnings) = self.stored.scan()?;
+        for (name, list) in self.lists.lock().unwrap().iter() {
+            match list {
+                Some(_) if !names.contains(name) => names.push(name.clone()),
+                Some(_) => {}
+                None => names.retain(|n| n != name),
+            }
+        }
+        names.sort();
+        Ok((names, warnings))
+    }
+
+    fn load(&self, name: &str) -> Result<TodoList> {
+        match self.lists.lock().unwrap().get(name) {
+            Some(Some(list)) => Ok(list.clone()),
+            Some(None) => Err(Error::ListNotFound(name.to_string())),
+            None => self.stored.load(name),
+        }
+    }
+
+    fn save(&self, list: &TodoList) -> Result<()> {
+        let mut lists = self.lists.lock().unwrap();
+        lists.insert(list.name.clone(), Some(list.clone()));
+        Ok(())
+    }
+
+    fn rename(&self, name: &str, new_name: &str) -> Result<()> {
+        let mut list = self.load(name)?;
+        let mut archive = self.load_archive(name)?;
+        self.delete(name)?;
+        list.name = new_name.to_string();
+        archive.name = new_name.to_string();
+        self.save(&list)?;
+        self.save_archive(&archive)
+    }
+
+    fn delete(&self, name: &str) -> Result<()> {
+        self.lists.lock().unwrap().insert(name.to_string(), None);
+        self.archives.lock().unwrap().remove(name);
+        Ok(())
+    }
+
+    fn modified(&self, name: &str) -> Option<u64> {
+        if self.lists.lock().unwrap().contains_key(name) {
+            return Some(date::now());
+        }
+        self.stored.modified(name)
+    }
+
+    fn load_archive(&self, name: &str) -> Result<TodoList> {
+        match self.archives.lock().unwrap().get(name) {
+            Some(archive) => Ok(archive.clone()),
+            None => self.stored.load_archive(name),
+        }
+    }
+
+    fn save_archive(&self, archive: &TodoList) -> Result<()> {
+        let mut archives = self.archives.lock().unwrap();
+        archives.insert(archive.name.clone(), archive.clone());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TodoPath;
+    use rustodo::storage::Directory;
+
+    const GROCERIES: &str = r#"{"name":"groceries","todos":[
+        {"id":1,"item":"Milk","priority":0,"tags":[],"done":false},
+        {"id":2,"item":"Bread","priority":0,"tags":[],"done":false}]}"#;
+
+    /// A todo path with the groceries list.
+    fn groceries(test: &str) -> TodoPath {
+        let todopath = TodoPath::new(test);
+        fs::write(todopath.0.join("groceries.json"), GROCERIES).unwrap();
+        todopath
+    }
+
+    fn replay_groceries(todopath: &TodoPath, script: &str, dump: Dump) -> String {
+        let stored = Box::new(Directory::new(todopath.path()));
+        replay(stored, todopath.path(), script, "groceries", dump).unwrap()
+    }
+
+    #[test]
+    fn replays_keys_on_the_screen() {
+        let todopath = groceries("screen");
+        let screen = replay_groceries(&todopath, "Enter a\n> Eggs\nEnter\n", Dump::Screen);
+
+        let mut expected = vec![
+            format!("╭{}groceries{}╮", "─".repeat(34), "─".repeat(35)),
+            format!("{:<79}│", "[x] Milk"),
+            format!("{:<79}│", "[ ] Bread"),
+            format!("{:<79}│", "[ ] Eggs"),
+            format!("╰{}1/3{}╯", "─".repeat(37), "─".repeat(38)),
+        ];
+        expected.resize(23, String::new());
+        expected.push(format!(
+            " NORMAL  groceries [+]{}? help  a create  s save  q/Esc quit",
+            " ".repeat(21)
+        ));
+        assert_eq!(screen.lines().collect::<Vec<_>>(), expected);
+    }
+
+    #[test]
+    fn replays_keys_on_the_list_at_a_fixed_time() {
+        let todopath = groceries("list");
+        let dump = replay_groceries(&todopath, "Enter\na\n> Eggs\nEnter", Dump::List);
+
+        let list: TodoList = serde_json::from_str(&dump).unwrap();
+        let items: Vec<(&str, bool)> = list
+            .todos
+            .iter()
+            .map(|todo| (todo.item.as_str(), todo.done))
+            .collect();
+        assert_eq!(items, [("Milk", true), ("Bread", false), ("Eggs", false)]);
+        assert_eq!(list.todos[0].completed_at, Some(START));
+        assert_eq!(list.todos[2].created_at, Some(START));
+    }
+
+    #[test]
+    fn keeps_the_stored_list() {
+        let todopath = groceries("stored");
+        let dump = replay_groceries(&todopath, "d y s", Dump::List);
+        let list: TodoList = serde_json::from_str(&dump).unwrap();
+        assert_eq!(list.todos.len(), 1);
+        assert_eq!(list.todos[0].item, "Bread");
+
+        let stored = fs::read_to_string(todopath.0.join("groceries.json")).unwrap();
+        assert_eq!(stored, GROCERIES);
+    }
+
+    #[test]
+    fn rejects_unknown_keys() {
+        assert_eq!(
+            parse_keys("# comment\na Foo").unwrap_err(),
+            "Unknown key 'Foo' on line 2"
+        );
+        let typed = parse_keys("> a b").unwrap();
+        assert_eq!(typed, [Key::Char('a'), Key::Char(' '), Key::Char('b')]);
+    }
+}
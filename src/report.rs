@@ -0,0 +1,58 @@
+//! Printable reports of the statistics of `rustodo::stats`.
+
+use crate::draw;
+use rustodo::date;
+use rustodo::stats::{Stats, DAYS, WEEKS};
+
+const SECONDS_PER_DAY: u64 = 86_400;
+const BAR_WIDTH: u16 = 40;
+
+pub fn stats(stats: &Stats, title: &str, now: u64) -> String {
+    let mut out = format!(
+        "{}\n\n{} completed, {} open\n\n",
+        draw::bold(title.to_string()),
+        stats.completed,
+        stats.open
+    );
+
+    out.push_str(&format!(
+        "Completed per day (last {} days)\n  {}  {} total\n\n",
+        DAYS,
+        draw::sparkline(&stats.per_day),
+        stats.per_day.iter().sum::<usize>()
+    ));
+
+    out.push_str(&format!("Completed per week (last {} weeks)\n", WEEKS));
+    let max = stats.per_week.iter().copied().max().unwrap_or(0);
+    let today = now / SECONDS_PER_DAY;
+    for (i, count) in stats.per_week.iter().enumerate() {
+        let weeks_ago = (WEEKS - 1 - i) as u64;
+        let start = (today.saturating_sub(weeks_ago * 7 + 6)) * SECONDS_PER_DAY;
+        out.push_str(&format!(
+            "  {} {:>3} {}\n",
+            date::format_date(start),
+            count,
+            draw::bar(*count, max, BAR_WIDTH)
+        ));
+    }
+
+    out.push('\n');
+    out.push_str(&format!(
+        "Average time to complete: {}\n",
+        format_optional(stats.average_completion)
+    ));
+    out.push_str(&format!(
+        "Open item age: {} average, {} oldest\n",
+        format_optional(stats.average_open_age),
+        format_optional(stats.oldest_open_age)
+    ));
+
+    out
+}
+
+fn format_optional(seconds: Option<u64>) -> String {
+    match seconds {
+        Some(seconds) => date::format_duration(seconds),
+        None => String::from("n/a"),
+    }
+}
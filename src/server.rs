@@ -0,0 +1,330 @@
+//! `rustodo serve`, the lists of the storage as a JSON API on localhost:
+//!
+//! - `GET /lists`: every list with its progress, as `rustodo list --json`
+//! - `GET /lists/<name>`: a list, as it is stored
+//! - `POST /lists/<name>/todos`: adds a todo, `{"item": "..."}`
+//! - `PATCH /lists/<name>/todos/<id>`: changes `item`, `priority`, `tags`,
+//!   `done` or `due_at` of a todo
+//! - `POST /lists/<name>/todos/<id>/toggle`: checks or unchecks a todo
+//! - `DELETE /lists/<name>/todos/<id>`
+//!
+//! Responses about a list carry its `ETag`. Requests that change a list can
+//! send it back as `If-Match`, and fail with 412 if the list changed since.
+//! Requests are handled one at a time, so saves never interleave.
+//!
+//! Requests from browser pages are only answered for the origins given with
+//! `--origin`, others are refused with 403. Bodies must be sent as
+//! `application/json`, which browsers don't send across origins without
+//! asking first, and the `Host` must be `127.0.0.1` or `localhost`, so
+//! pages can't reach the API through a domain of their own either.
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+use rustodo::storage::Storage;
+use rustodo::{date, Error, TodoChanges, TodoList};
+use tiny_http::{Header, Method, Request, Response, Server};
+
+pub const DEFAULT_PORT: u16 = 7878;
+
+struct Reply {
+    status: u16,
+    body: Option<String>,
+    etag: Option<String>,
+}
+
+/// An error response, with its status and message.
+struct Failure(u16, String);
+
+impl From<Error> for Failure {
+    fn from(err: Error) -> Self {
+        let status = match err {
+            Error::ListNotFound(_) | Error::TodoNotFound { .. } => 404,
+            Error::ListExists(_) => 409,
+            Error::InvalidTodo(_) | Error::InvalidName(_) => 400,
+            _ => 500,
+        };
+        Failure(status, err.to_string())
+    }
+}
+
+pub fn serve(storage: &dyn Storage, port: u16, origins: &[String]) -> Result<(), String> {
+    let server = match Server::http(("127.0.0.1", port)) {
+        Ok(server) => server,
+        Err(err) => return Err(format!("Unable to listen on port {}: {}", port, err)),
+    };
+    println!("Serving the todo lists on http://127.0.0.1:{}", port);
+
+    for mut request in server.incoming_requests() {
+        let reply = match check(&request, port, origins).and_then(|_| handle(storage, &mut request))
+        {
+            Ok(reply) => reply,
+            Err(Failure(status, message)) => Reply {
+                status,
+                body: Some(serde_json::json!({ "error": message }).to_string()),
+                etag: None,
+            },
+        };
+
+        let mut response = Response::from_string(reply.body.unwrap_or_default())
+            .with_status_code(reply.status)
+            .with_header(header("Content-Type", "application/json"))
+            .with_header(header("Vary", "Origin"));
+        if let Some(etag) = reply.etag {
+            response.add_header(header("ETag", &etag));
+        }
+        let origin = header_value(&request, "Origin").filter(|origin| origins.contains(origin));
+        let allowed = origin.is_some();
+        if let Some(origin) = origin {
+            response.add_header(header("Access-Control-Allow-Origin", &origin));
+            response.add_header(header("Access-Control-Expose-Headers", "ETag"));
+        }
+        if allowed && *request.method() == Method::Options {
+            response.add_header(header(
+                "Access-Control-Allow-Methods",
+                "GET, POST, PATCH, DELETE",
+            ));
+            response.add_header(header(
+                "Access-Control-Allow-Headers",
+                "Content-Type, If-Match, If-None-Match",
+            ));
+        }
+
+        // The client may be gone already, which only concerns that client
+        request.respond(response).ok();
+    }
+    Ok(())
+}
+
+/// Refuses requests of pages that aren't allowed to use the API.
+fn check(request: &Request, port: u16, origins: &[String]) -> Result<(), Failure> {
+    let host = header_value(request, "Host").unwrap_or_default();
+    let hosts = [format!("127.0.0.1:{}", port), format!("localhost:{}", port)];
+    if !hosts.iter().any(|h| h.eq_ignore_ascii_case(&host)) {
+        return Err(Failure(
+            403,
+            format!("Requests for host '{}' are refused", host),
+        ));
+    }
+
+    match header_value(request, "Origin") {
+        Some(origin) if !origins.contains(&origin) => Err(Failure(
+            403,
+            format!("Requests from '{}' are refused, see --origin", origin),
+        )),
+        _ => Ok(()),
+    }
+}
+
+fn handle(storage: &dyn Storage, request: &mut Request) -> Result<Reply, Failure> {
+    let method = request.method().clone();
+    let path = request
+        .url()
+        .split('?')
+        .next()
+        .unwrap_or_default()
+        .to_string();
+    let segments: Vec<String> = path
+        .split('/')
+        .filter(|s| !s.is_empty())
+        .map(percent_decode)
+        .collect();
+    let segments: Vec<&str> = segments.iter().map(|s| s.as_str()).collect();
+
+    match (&method, segments.as_slice()) {
+        (Method::Options, _) => Ok(Reply {
+            status: 204,
+            body: None,
+            etag: None,
+        }),
+        (Method::Get, ["lists"]) => list_lists(storage),
+        (Method::Get, ["lists", name]) => {
+            let list = storage.load(name)?;
+            let etag = etag(&list);
+            if header_value(request, "If-None-Match").as_deref() == Some(etag.as_str()) {
+                return Ok(Reply {
+                    status: 304,
+                    body: None,
+                    etag: Some(etag),
+                });
+            }
+            Ok(Reply {
+                status: 200,
+                body: Some(list.to_json()),
+                etag: Some(etag),
+            })
+        }
+        (Method::Post, ["lists", name, "todos"]) => {
+            let changes = read_changes(request)?;
+            change(storage, request, name, 201, |list| {
+                Ok(Some(list.add(changes)?))
+            })
+        }
+        (Method::Patch, ["lists", name, "todos", id]) => {
+            let id = parse_id(id)?;
+            let changes = read_changes(request)?;
+            change(storage, request, name, 200, |list| {
+                let index = position(list, id)?;
+                list.todos[index].apply(changes)?;
+                Ok(Some(index))
+            })
+        }
+        (Method::Post, ["lists", name, "todos", id, "toggle"]) => {
+            let id = parse_id(id)?;
+            change(storage, request, name, 200, |list| {
+                let index = position(list, id)?;
+                list.todos[index].toggle();
+                Ok(Some(index))
+            })
+        }
+        (Method::Delete, ["lists", name, "todos", id]) => {
+            let id = parse_id(id)?;
+            change(storage, request, name, 204, |list| {
+                let index = position(list, id)?;
+                list.todos.remove(index);
+                Ok(None)
+            })
+        }
+        _ => Err(Failure(404, format!("No endpoint for {} {}", method, path))),
+    }
+}
+
+fn list_lists(storage: &dyn Storage) -> Result<Reply, Failure> {
+    let (summaries, warnings) = storage.summaries()?;
+
+    let lists: Vec<serde_json::Value> = summaries
+        .iter()
+        .map(|s| {
+            serde_json::json!({
+                "name": s.name,
+                "format": s.format.extension(),
+                "done": s.done,
+                "open": s.open(),
+                "total": s.total,
+                "progress": s.progress(),
+                "tags": s.tags,
+                "modified": s.modified.map(date::format_datetime),
+            })
+        })
+        .collect();
+    Ok(Reply {
+        status: 200,
+        body: Some(serde_json::json!({ "lists": lists, "warnings": warnings }).to_string()),
+        etag: None,
+    })
+}
+
+/// Loads a list, checks `If-Match`, changes and saves it. The change returns
+/// the index of the todo to reply with, if any.
+fn change(
+    storage: &dyn Storage,
+    request: &Request,
+    name: &str,
+    status: u16,
+    f: impl FnOnce(&mut TodoList) -> Result<Option<usize>, Failure>,
+) -> Result<Reply, Failure> {
+    let mut list = storage.load(name)?;
+
+    if let Some(expected) = header_value(request, "If-Match") {
+        if expected != "*" && expected != etag(&list) {
+            return Err(Failure(
+                412,
+                format!("'{}' was changed by someone else, reload it", list.name),
+            ));
+        }
+    }
+
+    let body = f(&mut list)?.map(|index| {
+        let todo = &list.todos[index];
+        serde_json::to_string(todo).expect("Error serializing json")
+    });
+    storage.save(&list)?;
+
+    Ok(Reply {
+        status,
+        body,
+        etag: Some(etag(&list)),
+    })
+}
+
+fn read_changes(request: &mut Request) -> Result<TodoChanges, Failure> {
+    let content_type = header_value(request, "Content-Type").unwrap_or_default();
+    let media_type = content_type.split(';').next().unwrap_or_default().trim();
+    if !media_type.eq_ignore_ascii_case("application/json") {
+        return Err(Failure(
+            415,
+            String::from("The request must be sent as application/json"),
+        ));
+    }
+
+    let mut body = String::new();
+    if let Err(err) = request.as_reader().read_to_string(&mut body) {
+        return Err(Failure(400, format!("Unable to read the request: {}", err)));
+    }
+    match serde_json::from_str(&body) {
+        Ok(changes) => Ok(changes),
+        Err(err) => Err(Failure(400, format!("Invalid todo: {}", err))),
+    }
+}
+
+fn position(list: &TodoList, id: i16) -> Result<usize, Failure> {
+    match list.todos.iter().position(|todo| todo.id == id) {
+        Some(index) => Ok(index),
+        None => Err(Error::TodoNotFound {
+            list: list.name.clone(),
+            id,
+        }
+        .into()),
+    }
+}
+
+fn parse_id(id: &str) -> Result<i16, Failure> {
+    match id.parse() {
+        Ok(id) => Ok(id),
+        Err(_) => Err(Failure(400, format!("Invalid todo id '{}'", id))),
+    }
+}
+
+/// A tag that changes whenever the stored list changes.
+fn etag(list: &TodoList) -> String {
+    let mut hasher = DefaultHasher::new();
+    list.serialize().hash(&mut hasher);
+    format!("\"{:016x}\"", hasher.finish())
+}
+
+fn header_value(request: &Request, name: &str) -> Option<String> {
+    request
+        .headers()
+        .iter()
+        .find(|h| h.field.as_str().as_str().eq_ignore_ascii_case(name))
+        .map(|h| h.value.as_str().trim().to_string())
+}
+
+fn header(name: &str, value: &str) -> Header {
+    Header::from_bytes(name.as_bytes(), value.as_bytes()).expect("Invalid header")
+}
+
+/// Decodes the `%XX` escapes of a path segment, so list names may contain
+/// spaces and other reserved characters.
+fn percent_decode(segment: &str) -> String {
+    let bytes = segment.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = bytes
+            .get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], escaped) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
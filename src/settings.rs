@@ -1,6 +1,9 @@
 use std::{fs::{File, self}, io::{Read, Write}};
 
 use home::home_dir;
+use std::collections::HashMap;
+
+use crate::error::{Error, Result};
 use serde::{Serialize, Deserialize};
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -8,15 +11,83 @@ pub struct Settings {
     pub todopath: String,
     pub checked_symbol: String,
     pub unchecked_symbol: String,
+    /// Report mouse events to the panel, which disables the terminal's own
+    /// text selection while a list is open.
+    #[serde(default = "enabled")]
+    pub mouse: bool,
+    #[serde(default = "enabled")]
+    pub status_bar: bool,
+    /// Overrides of the default key bindings, see `keymap`.
+    #[serde(default)]
+    pub keybindings: HashMap<String, String>,
+    #[serde(default)]
+    pub message_durations: MessageDurations,
+    #[serde(default)]
+    pub backend: Backend,
+    /// Database file of the sqlite backend, `<todopath>/todos.sqlite` by default.
+    #[serde(default)]
+    pub database: Option<String>,
+    /// Keep the todo path in a git repository, committing every change.
+    /// Only the json backend can be kept in git.
+    #[serde(default)]
+    pub git: bool,
+    /// Url or path of the repository `rustodo sync` pulls from and pushes to.
+    #[serde(default)]
+    pub git_remote: Option<String>,
+    /// How often, in seconds, the panel saves unsaved changes. 0 leaves
+    /// saving to the user.
+    #[serde(default)]
+    pub autosave: u64,
+    /// Warn in the panel when open todos become due.
+    #[serde(default = "enabled")]
+    pub reminders: bool,
+    /// Show the time (UTC) in the status bar.
+    #[serde(default)]
+    pub clock: bool,
+}
+
+/// Where lists are stored, see `storage`.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    /// A `.json` or todo.txt file per list in the todo path.
+    #[default]
+    Json,
+    Sqlite,
+}
+
+/// How long the panel shows messages of each severity, in milliseconds.
+/// Messages with a duration of 0 stay until they are dismissed.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(default)]
+pub struct MessageDurations {
+    pub info: u64,
+    pub success: u64,
+    pub warning: u64,
+    pub error: u64,
+}
+
+impl Default for MessageDurations {
+    fn default() -> Self {
+        MessageDurations {
+            info: 1000,
+            success: 1000,
+            warning: 3000,
+            error: 0,
+        }
+    }
 }
 
 impl Settings {
-    pub fn load() -> Result<Settings, String> {
+    /// Loads `~/.config/todo/config.json`, which is created with the
+    /// defaults if it doesn't exist.
+    #[allow(clippy::single_match, clippy::needless_return)]
+    pub fn load() -> Result<Settings> {
 
 
         let homedir = match home_dir() {
             Some(path) => path.display().to_string(),
-            None => return Err(String::from("Unable to load home path")),
+            None => return Err(Error::NoHomeDir),
         };
 
         let config_path = format!("{}/.config/todo/config.json", homedir);
@@ -27,50 +98,57 @@ impl Settings {
         };
         
 
-        match fs::create_dir_all(format!("{}/.config/todo", homedir)) {
-            Err(err) => return Err(format!("Unable to create config folder: {}", err)),
+        let config_dir = format!("{}/.config/todo", homedir);
+        match fs::create_dir_all(&config_dir) {
+            Err(err) => return Err(Error::io("create", config_dir, err)),
             _ => (),
         };
 
         let settings = Settings::default(homedir);
  
         let mut config = match File::create(config_path.clone()) {
-            Err(err) => return Err(format!("Unable to create configuration file at path '{}': {}", config_path, err)),
+            Err(err) => return Err(Error::io("create", config_path, err)),
             Ok(file) => file,
         };
 
-        match config.write_all(settings.as_json().unwrap().as_bytes()) {
+        match config.write_all(settings.as_json().as_bytes()) {
             Ok(_) => return Ok(settings),
-            Err(err) => return Err(format!("Unable to write to configuration file: {}", err))
+            Err(err) => return Err(Error::io("write", config_path, err))
         };
         
     }
 
-    fn as_json(&self) -> Result<String, String> {
+    /// The default settings for the lists in `todopath`, which are the same
+    /// on every machine.
+    pub fn with_todopath(todopath: String) -> Settings {
+        Settings {
+            todopath,
+            ..Settings::default(String::new())
+        }
+    }
 
-        match serde_json::to_string(self) {
-            Ok(res) => return Ok(res),
-            Err(err) => return Err(format!("Unable to serialize configuration: {}", err))
-        };
+    fn as_json(&self) -> String {
+        serde_json::to_string(self).expect("Error serializing configuration")
     }
 
-    fn load_existing(path: String) -> Result<Settings, String> {
-       let mut file = match File::open(path) {
+    #[allow(clippy::single_match)]
+    fn load_existing(path: String) -> Result<Settings> {
+       let mut file = match File::open(&path) {
            Ok(file) => file,
-           Err(err) => return Err(format!("Unable to open configuration file: {}", err))
+           Err(err) => return Err(Error::io("open", path, err))
        };
 
 
        let mut data = String::new();
 
        match file.read_to_string(&mut data) {
-           Err(err) => return Err(format!("Unable to read configuration file: {}", err)),
+           Err(err) => return Err(Error::io("read", path, err)),
            _ => (),
        };
 
        match serde_json::from_str(data.as_str()) {
            Ok(settings) => Ok(settings),
-           Err(err) => Err(format!("Unable to parse configuration: {}", err)),
+           Err(err) => Err(Error::parse(path, err)),
        }
     }
 
@@ -80,6 +158,21 @@ impl Settings {
             todopath: format!("{}/todos", home_path),
             checked_symbol: String::from("[x]"),
             unchecked_symbol: String::from("[ ]"),
+            mouse: true,
+            status_bar: true,
+            keybindings: HashMap::new(),
+            message_durations: MessageDurations::default(),
+            backend: Backend::Json,
+            database: None,
+            git: false,
+            git_remote: None,
+            autosave: 0,
+            reminders: true,
+            clock: false,
         } 
     }
-} 
+}
+
+fn enabled() -> bool {
+    true
+}
@@ -0,0 +1,155 @@
+//! Completion statistics over the timestamps recorded on each todo.
+
+use crate::todo::Todo;
+
+const SECONDS_PER_DAY: u64 = 86_400;
+pub const DAYS: usize = 14;
+pub const WEEKS: usize = 8;
+
+pub struct Stats {
+    /// Completions per calendar day, oldest first and ending today.
+    pub per_day: [usize; DAYS],
+    /// Completions per seven day period, oldest first and ending today.
+    pub per_week: [usize; WEEKS],
+    pub completed: usize,
+    pub open: usize,
+    pub average_completion: Option<u64>,
+    pub average_open_age: Option<u64>,
+    pub oldest_open_age: Option<u64>,
+}
+
+pub fn collect<'a>(todos: impl Iterator<Item = &'a Todo>, now: u64) -> Stats {
+    let today = now / SECONDS_PER_DAY;
+    let mut stats = Stats {
+        per_day: [0; DAYS],
+        per_week: [0; WEEKS],
+        completed: 0,
+        open: 0,
+        average_completion: None,
+        average_open_age: None,
+        oldest_open_age: None,
+    };
+    let mut completion_times = Vec::new();
+    let mut open_ages = Vec::new();
+
+    for todo in todos {
+        if !todo.done {
+            stats.open += 1;
+            if let Some(created_at) = todo.created_at {
+                open_ages.push(now.saturating_sub(created_at));
+            }
+            continue;
+        }
+
+        stats.completed += 1;
+        let completed_at = match todo.completed_at {
+            Some(completed_at) => completed_at,
+            None => continue,
+        };
+
+        let days_ago = today.saturating_sub(completed_at / SECONDS_PER_DAY) as usize;
+        if days_ago < DAYS {
+            stats.per_day[DAYS - 1 - days_ago] += 1;
+        }
+        if days_ago / 7 < WEEKS {
+            stats.per_week[WEEKS - 1 - days_ago / 7] += 1;
+        }
+        if let Some(created_at) = todo.created_at {
+            completion_times.push(completed_at.saturating_sub(created_at));
+        }
+    }
+
+    stats.average_completion = average(&completion_times);
+    stats.average_open_age = average(&open_ages);
+    stats.oldest_open_age = open_ages.iter().copied().max();
+    stats
+}
+
+fn average(values: &[u64]) -> Option<u64> {
+    match values.len() {
+        0 => None,
+        len => Some(values.iter().sum::<u64>() / len as u64),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: u64 = SECONDS_PER_DAY;
+    /// Noon of 2024-01-15, so completions earlier today are on the same day.
+    const NOW: u64 = 1_705_320_000;
+
+    fn done(created_at: Option<u64>, completed_at: Option<u64>) -> Todo {
+        Todo {
+            done: true,
+            created_at,
+            completed_at,
+            ..Default::default()
+        }
+    }
+
+    fn open(created_at: Option<u64>) -> Todo {
+        Todo {
+            created_at,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn counts_completions_per_day_and_week() {
+        let todos = [
+            done(Some(NOW - 2 * 3600), Some(NOW - 3600)),
+            done(None, Some(NOW - 13 * DAY)),
+            done(None, Some(NOW - 14 * DAY)),
+            done(None, Some(NOW - 8 * 7 * DAY)),
+        ];
+        let stats = collect(todos.iter(), NOW);
+
+        let mut per_day = [0; DAYS];
+        per_day[DAYS - 1] = 1;
+        per_day[0] = 1;
+        assert_eq!(stats.per_day, per_day);
+
+        let mut per_week = [0; WEEKS];
+        // Today in this week, 13 days ago in the last one, 14 days ago the
+        // one before, 8 weeks ago is too old
+        per_week[WEEKS - 1] = 1;
+        per_week[WEEKS - 2] = 1;
+        per_week[WEEKS - 3] = 1;
+        assert_eq!(stats.per_week, per_week);
+        assert_eq!(stats.completed, 4);
+    }
+
+    #[test]
+    fn averages_only_todos_with_both_timestamps() {
+        let todos = [
+            done(Some(NOW - 3 * DAY), Some(NOW - DAY)),
+            done(Some(NOW - 4 * DAY), Some(NOW)),
+            done(None, Some(NOW)),
+            done(Some(NOW), None),
+            open(Some(NOW - DAY)),
+            open(Some(NOW - 3 * DAY)),
+            open(None),
+        ];
+        let stats = collect(todos.iter(), NOW);
+
+        assert_eq!((stats.completed, stats.open), (4, 3));
+        assert_eq!(stats.average_completion, Some(3 * DAY));
+        assert_eq!(stats.average_open_age, Some(2 * DAY));
+        assert_eq!(stats.oldest_open_age, Some(3 * DAY));
+        assert_eq!(stats.per_day[DAYS - 1], 2);
+        assert_eq!(stats.per_day[DAYS - 2], 1);
+    }
+
+    #[test]
+    fn has_no_averages_without_timestamps() {
+        let todos = [done(None, None), open(None)];
+        let stats = collect(todos.iter(), NOW);
+
+        assert_eq!(stats.per_day, [0; DAYS]);
+        assert_eq!(stats.average_completion, None);
+        assert_eq!(stats.average_open_age, None);
+        assert_eq!(stats.oldest_open_age, None);
+    }
+}
@@ -0,0 +1,172 @@
+//! Where todo lists and their archives are kept. `Directory` stores every
+//! list as a file in the todo path and is the default, `Sqlite` keeps all
+//! lists in one database, see `Settings::backend`. `Git` is a `Directory`
+//! that commits every change, see `Settings::git`.
+
+mod directory;
+mod git;
+mod index;
+mod sqlite;
+
+pub use directory::Directory;
+pub use git::{Commit, Git};
+pub use sqlite::Sqlite;
+
+use crate::error::{Error, Result};
+use crate::settings::{Backend, Settings};
+use crate::todo::{Format, Todo, TodoList};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+pub trait Storage: Send {
+    /// Names of all lists, sorted, and warnings about stored entries that
+    /// were skipped.
+    fn scan(&self) -> Result<(Vec<String>, Vec<String>)>;
+
+    fn load(&self, name: &str) -> Result<TodoList>;
+
+    /// Saves a list under its name, replacing the stored list of that name.
+    fn save(&self, list: &TodoList) -> Result<()>;
+
+    /// Renames a list along with its archive.
+    fn rename(&self, name: &str, new_name: &str) -> Result<()>;
+
+    /// Removes a list along with its archive. Removed lists are kept aside
+    /// rather than deleted, so a removal can be undone by hand.
+    fn delete(&self, name: &str) -> Result<()>;
+
+    /// Unix time of the last change of a list, if it is known.
+    fn modified(&self, name: &str) -> Option<u64>;
+
+    /// The archive of a list, which is empty if nothing was archived yet.
+    fn load_archive(&self, name: &str) -> Result<TodoList>;
+
+    fn save_archive(&self, archive: &TodoList) -> Result<()>;
+
+    /// A file that changes whenever the list is changed, so it can be
+    /// watched for changes made elsewhere.
+    fn watch_path(&self, _name: &str) -> Option<PathBuf> {
+        None
+    }
+
+    fn names(&self) -> Result<Vec<String>> {
+        self.scan().map(|(names, _)| names)
+    }
+
+    /// Counts and tags of every list, sorted by name, and warnings about the
+    /// lists that were skipped.
+    fn summaries(&self) -> Result<(Vec<Summary>, Vec<String>)> {
+        let (names, mut warnings) = self.scan()?;
+        let mut summaries = Vec::new();
+        for name in names {
+            match self.load(&name) {
+                Ok(list) => summaries.push(Summary::new(&list, self.modified(&name))),
+                Err(err) => warnings.push(format!("Skipping '{}': {}", name, err)),
+            }
+        }
+        Ok((summaries, warnings))
+    }
+
+    fn exists(&self, name: &str) -> Result<bool> {
+        Ok(self.names()?.iter().any(|n| n == name))
+    }
+
+    /// The todos of all lists matching a query, with the name of their list.
+    fn find(&self, query: &Query) -> Result<Vec<(String, Todo)>> {
+        let mut found = Vec::new();
+        for name in self.names()? {
+            let list = self.load(&name)?;
+            for todo in list.todos.into_iter().filter(|todo| query.matches(todo)) {
+                found.push((list.name.clone(), todo));
+            }
+        }
+        Ok(found)
+    }
+}
+
+/// What `rustodo list` shows of a list, without its todos.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Summary {
+    pub name: String,
+    #[serde(skip)]
+    pub format: Format,
+    pub done: usize,
+    pub total: usize,
+    /// Tags of the todos, sorted and without duplicates.
+    pub tags: Vec<String>,
+    pub modified: Option<u64>,
+}
+
+impl Summary {
+    pub fn new(list: &TodoList, modified: Option<u64>) -> Self {
+        let mut tags: Vec<String> = list
+            .todos
+            .iter()
+            .flat_map(|todo| todo.tags.iter().cloned())
+            .collect();
+        tags.sort();
+        tags.dedup();
+        Summary {
+            name: list.name.clone(),
+            format: list.format,
+            done: list.completed(),
+            total: list.total(),
+            tags,
+            modified,
+        }
+    }
+
+    pub fn open(&self) -> usize {
+        self.total - self.done
+    }
+
+    pub fn progress(&self) -> f64 {
+        match self.total {
+            0 => 0.0,
+            total => self.done as f64 / total as f64,
+        }
+    }
+}
+
+/// Filter for `Storage::find`, unset fields match every todo.
+#[derive(Debug, Clone, Default)]
+pub struct Query {
+    /// Part of the item text, ignoring case.
+    pub text: Option<String>,
+    pub tag: Option<String>,
+    pub done: Option<bool>,
+}
+
+impl Query {
+    pub fn matches(&self, todo: &Todo) -> bool {
+        let text = self.text.as_ref().is_none_or(|text| {
+            todo.item
+                .to_lowercase()
+                .contains(text.to_lowercase().as_str())
+        });
+        let tag = self
+            .tag
+            .as_ref()
+            .is_none_or(|tag| todo.tags.iter().any(|t| t == tag));
+        let done = self.done.is_none_or(|done| todo.done == done);
+        text && tag && done
+    }
+}
+
+/// Opens the backend selected in the settings.
+pub fn open(settings: &Settings) -> Result<Box<dyn Storage>> {
+    match settings.backend {
+        Backend::Json if settings.git => Ok(Box::new(Git::open(&settings.todopath)?)),
+        Backend::Json => Ok(Box::new(Directory::new(&settings.todopath))),
+        Backend::Sqlite if settings.git => Err(Error::InvalidSettings(String::from(
+            "only the json backend can be kept in git",
+        ))),
+        Backend::Sqlite => {
+            let path = match &settings.database {
+                Some(path) => path.clone(),
+                None => format!("{}/{}", settings.todopath, sqlite::DEFAULT_FILE),
+            };
+            Ok(Box::new(Sqlite::open(&path)?))
+        }
+    }
+}
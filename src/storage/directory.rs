@@ -0,0 +1,365 @@
+//! Lists stored as `<todopath>/<name>.json` or `<name>.txt` files. Archives
+//! are stored as `<todopath>/.archive/<name>.json` so they stay out of
+//! `rustodo list`, and removed lists are moved into `<todopath>/.trash`.
+//! Their summaries are cached in an index, see `index`.
+
+use super::index::{self, Index, Stamp};
+use super::{Storage, Summary};
+use crate::date;
+use crate::error::{Error, Result};
+use crate::lists;
+use crate::todo::{Format, TodoList};
+use crate::todotxt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::UNIX_EPOCH;
+
+const ARCHIVE_DIR: &str = ".archive";
+const TRASH_DIR: &str = ".trash";
+
+/// Pattern of the files `Directory::write` writes before renaming them.
+pub const TMP_FILES: &str = "*.tmp";
+
+/// Makes the names of temporary files unique within the process.
+static TMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+pub struct Directory {
+    path: String,
+}
+
+impl Directory {
+    pub fn new(path: &str) -> Self {
+        Directory {
+            path: path.to_string(),
+        }
+    }
+
+    pub fn path(&self, name: &str, format: Format) -> PathBuf {
+        Path::new(&self.path).join(format!("{}.{}", name, format.extension()))
+    }
+
+    pub fn archive_path(&self, name: &str) -> PathBuf {
+        Path::new(&self.path)
+            .join(ARCHIVE_DIR)
+            .join(format!("{}.json", name))
+    }
+
+    fn index_path(&self) -> PathBuf {
+        Path::new(&self.path).join(index::FILE)
+    }
+
+    /// The format a list is stored in, json unless only a todo.txt file
+    /// exists.
+    fn format(&self, name: &str) -> Format {
+        [Format::Json, Format::TodoTxt]
+            .into_iter()
+            .find(|f| self.path(name, *f).exists())
+            .unwrap_or(Format::Json)
+    }
+
+    /// Writes next to the file and renames, so it is never left half written.
+    /// The temporary file is unique, so concurrent writers don't mix.
+    fn write(path: &Path, data: &str) -> Result<()> {
+        let tmp_path = PathBuf::from(format!(
+            "{}.{}.{}.tmp",
+            path.display(),
+            process::id(),
+            TMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        match fs::write(&tmp_path, data).and_then(|_| fs::rename(&tmp_path, path)) {
+            Ok(_) => Ok(()),
+            Err(err) => {
+                fs::remove_file(&tmp_path).ok();
+                Err(Error::io("write", path, err))
+            }
+        }
+    }
+
+    /// Updates the summary of a list that was just saved.
+    fn index(&self, list: &TodoList) {
+        let Some(stamp) = Stamp::of(&self.path(&list.name, list.format)) else {
+            return;
+        };
+        let mut index = Index::load(&self.index_path());
+        let summary = Summary::new(list, self.modified(&list.name));
+        index.insert(&list.name, stamp, summary);
+        // The index is only a cache, it is rebuilt when it can't be written
+        Directory::write(&self.index_path(), &index.to_json()).ok();
+    }
+}
+
+impl Storage for Directory {
+    fn scan(&self) -> Result<(Vec<String>, Vec<String>)> {
+        lists::scan(&self.path)
+    }
+
+    fn load(&self, name: &str) -> Result<TodoList> {
+        let list_name = name
+            .strip_suffix(".json")
+            .or_else(|| name.strip_suffix(".txt"))
+            .unwrap_or(name);
+        lists::validate(list_name)?;
+        let format = self.format(list_name);
+        let path = self.path(list_name, format);
+
+        let data = match fs::read_to_string(&path) {
+            Ok(data) => data,
+            Err(_) if !path.exists() => return Err(Error::ListNotFound(list_name.to_string())),
+            Err(err) => return Err(Error::io("read", path, err)),
+        };
+
+        match format {
+            Format::Json => match serde_json::from_str(data.as_str()) {
+                Ok(todo_list) => Ok(todo_list),
+                Err(err) => Err(Error::parse(path, err)),
+            },
+            Format::TodoTxt => Ok(todotxt::parse(list_name.to_string(), data.as_str())),
+        }
+    }
+
+    fn save(&self, list: &TodoList) -> Result<()> {
+        lists::validate(&list.name)?;
+        Directory::write(&self.path(&list.name, list.format), &list.serialize())?;
+        self.index(list);
+        Ok(())
+    }
+
+    /// Loads only the lists whose files changed since they were indexed.
+    fn summaries(&self) -> Result<(Vec<Summary>, Vec<String>)> {
+        let (names, mut warnings) = self.scan()?;
+        let mut index = Index::load(&self.index_path());
+        let mut changed = index.retain(&names);
+        let mut summaries = Vec::new();
+
+        for name in names {
+            let format = self.format(&name);
+            let stamp = Stamp::of(&self.path(&name, format));
+            if let Some(summary) = stamp.and_then(|stamp| index.get(&name, stamp)) {
+                summaries.push(Summary {
+                    format,
+                    ..summary.clone()
+                });
+                continue;
+            }
+
+            let list = match self.load(&name) {
+                Ok(list) => list,
+                Err(err) => {
+                    warnings.push(format!("Skipping '{}': {}", name, err));
+                    continue;
+                }
+            };
+            let summary = Summary::new(&list, self.modified(&name));
+            if let Some(stamp) = stamp {
+                index.insert(&name, stamp, summary.clone());
+                changed = true;
+            }
+            summaries.push(summary);
+        }
+
+        if changed {
+            Directory::write(&self.index_path(), &index.to_json()).ok();
+        }
+        Ok((summaries, warnings))
+    }
+
+    fn rename(&self, name: &str, new_name: &str) -> Result<()> {
+        lists::validate(new_name)?;
+        let mut list = self.load(name)?;
+        let old_path = self.path(name, list.format);
+        list.name = new_name.to_string();
+        self.save(&list)?;
+
+        if let Err(err) = fs::remove_file(&old_path) {
+            return Err(Error::io("remove", old_path, err));
+        }
+
+        let old_archive = self.archive_path(name);
+        if !old_archive.exists() {
+            return Ok(());
+        }
+        let mut archive = self.load_archive(name)?;
+        archive.name = new_name.to_string();
+        self.save_archive(&archive)?;
+
+        match fs::remove_file(&old_archive) {
+            Ok(_) => Ok(()),
+            Err(err) => Err(Error::io("remove", old_archive, err)),
+        }
+    }
+
+    fn delete(&self, name: &str) -> Result<()> {
+        lists::validate(name)?;
+        let format = self.format(name);
+        let trash = Path::new(&self.path).join(TRASH_DIR);
+        if let Err(err) = fs::create_dir_all(&trash) {
+            return Err(Error::io("create", trash, err));
+        }
+
+        // Earlier removals of lists with the same name are kept
+        let mut trashed_name = name.to_string();
+        if trash
+            .join(format!("{}.{}", trashed_name, format.extension()))
+            .exists()
+        {
+            trashed_name = format!("{}.{}", name, date::now());
+        }
+
+        let mut moves = vec![(
+            self.path(name, format),
+            trash.join(format!("{}.{}", trashed_name, format.extension())),
+        )];
+        let archive_path = self.archive_path(name);
+        if archive_path.exists() {
+            moves.push((
+                archive_path,
+                trash.join(format!("{}.archive.json", trashed_name)),
+            ));
+        }
+
+        for (from, to) in moves {
+            if let Err(err) = fs::rename(&from, &to) {
+                return Err(Error::io("move", from, err));
+            }
+        }
+        Ok(())
+    }
+
+    fn modified(&self, name: &str) -> Option<u64> {
+        lists::validate(name).ok()?;
+        fs::metadata(self.path(name, self.format(name)))
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .map(|duration| duration.as_secs())
+    }
+
+    fn load_archive(&self, name: &str) -> Result<TodoList> {
+        lists::validate(name)?;
+        let path = self.archive_path(name);
+
+        let data = match fs::read_to_string(&path) {
+            Ok(data) => data,
+            Err(_) if !path.exists() => return Ok(TodoList::new(name.to_string())),
+            Err(err) => return Err(Error::io("read", path, err)),
+        };
+
+        match serde_json::from_str(data.as_str()) {
+            Ok(archive) => Ok(archive),
+            Err(err) => Err(Error::parse(path, err)),
+        }
+    }
+
+    fn save_archive(&self, archive: &TodoList) -> Result<()> {
+        lists::validate(&archive.name)?;
+        let archive_dir = Path::new(&self.path).join(ARCHIVE_DIR);
+
+        if let Err(err) = fs::create_dir_all(&archive_dir) {
+            return Err(Error::io("create", archive_dir, err));
+        }
+
+        Directory::write(&self.archive_path(&archive.name), &archive.to_json())
+    }
+
+    fn watch_path(&self, name: &str) -> Option<PathBuf> {
+        lists::validate(name).ok()?;
+        Some(self.path(name, self.format(name)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TodoPath;
+    use crate::todo::Todo;
+
+    fn list(name: &str, items: &[&str]) -> TodoList {
+        let mut list = TodoList::new(name.to_string());
+        for item in items {
+            list.todos.push(Todo::new(list.next_id(), item.to_string()));
+        }
+        list
+    }
+
+    fn totals(directory: &Directory) -> Vec<(String, usize)> {
+        let (summaries, _) = directory.summaries().unwrap();
+        summaries
+            .into_iter()
+            .map(|summary| (summary.name, summary.total))
+            .collect()
+    }
+
+    #[test]
+    fn summaries_come_from_the_index_while_files_are_unchanged() {
+        let todopath = TodoPath::new("cached");
+        let directory = Directory::new(todopath.path());
+        directory.save(&list("home", &["a", "b"])).unwrap();
+
+        // A summary that doesn't match the file shows the index was used
+        let mut index = Index::load(&directory.index_path());
+        let path = directory.path("home", Format::Json);
+        let summary = Summary::new(&list("home", &["x"]), None);
+        index.insert("home", Stamp::of(&path).unwrap(), summary);
+        fs::write(directory.index_path(), index.to_json()).unwrap();
+
+        assert_eq!(totals(&directory), [(String::from("home"), 1)]);
+    }
+
+    #[test]
+    fn changed_and_removed_files_invalidate_the_index() {
+        let todopath = TodoPath::new("invalidated");
+        let directory = Directory::new(todopath.path());
+        directory.save(&list("home", &["a", "b"])).unwrap();
+        directory.save(&list("work", &["c"])).unwrap();
+        assert_eq!(
+            totals(&directory),
+            [(String::from("home"), 2), (String::from("work"), 1)]
+        );
+
+        let changed = list("home", &["a", "b", "c"]);
+        fs::write(directory.path("home", Format::Json), changed.to_json()).unwrap();
+        fs::remove_file(directory.path("work", Format::Json)).unwrap();
+        assert_eq!(totals(&directory), [(String::from("home"), 3)]);
+
+        let index = Index::load(&directory.index_path());
+        let stamp = Stamp::of(&directory.path("home", Format::Json)).unwrap();
+        assert_eq!(index.get("home", stamp).map(|s| s.total), Some(3));
+        assert!(!index.to_json().contains("work"));
+    }
+
+    #[test]
+    fn indexes_copies_under_their_file_names() {
+        let todopath = TodoPath::new("copied");
+        let directory = Directory::new(todopath.path());
+        directory.save(&list("work", &["a"])).unwrap();
+        directory.summaries().unwrap();
+        fs::write(
+            directory.path("work2", Format::Json),
+            list("work", &["a", "b"]).to_json(),
+        )
+        .unwrap();
+        directory.summaries().unwrap();
+
+        let index = Index::load(&directory.index_path());
+        let stamp = |name| Stamp::of(&directory.path(name, Format::Json)).unwrap();
+        assert_eq!(index.get("work", stamp("work")).map(|s| s.total), Some(1));
+        assert_eq!(index.get("work2", stamp("work2")).map(|s| s.total), Some(2));
+    }
+
+    #[test]
+    fn leaves_no_temporary_files() {
+        let todopath = TodoPath::new("tmp");
+        let directory = Directory::new(todopath.path());
+        directory.save(&list("home", &["a"])).unwrap();
+        directory.summaries().unwrap();
+
+        let mut files: Vec<String> = fs::read_dir(&todopath.0)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        files.sort();
+        assert_eq!(files, [index::FILE, "home.json"]);
+    }
+}
@@ -0,0 +1,313 @@
+//! A todo path that is a git repository. Every change made through the
+//! storage is committed with a message describing it, e.g.
+//! `lists/work: completed 'ship release'`, and the repository can be synced
+//! with a remote.
+
+use super::{directory, index, Directory, Storage, Summary};
+use crate::error::{Error, Result};
+use crate::todo::{Todo, TodoList};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Longest todo text quoted in a commit message.
+const MAX_ITEM_LENGTH: usize = 50;
+/// Most changes listed in a commit message before they are only counted.
+const MAX_CHANGES: usize = 3;
+const REMOTE: &str = "origin";
+
+pub struct Git {
+    directory: Directory,
+    path: String,
+    /// Whether git knows who commits, otherwise a placeholder is used.
+    identity: bool,
+}
+
+/// A commit that changed a list.
+pub struct Commit {
+    pub hash: String,
+    pub date: String,
+    pub message: String,
+}
+
+impl Git {
+    /// Opens the todo path as a repository. A todo path that isn't one yet is
+    /// initialized, committing the lists it already holds.
+    pub fn open(path: &str) -> Result<Self> {
+        let mut git = Git {
+            directory: Directory::new(path),
+            path: path.to_string(),
+            identity: true,
+        };
+        git.identity = git.git(&["config", "user.name"]).is_ok();
+
+        let created = !Path::new(path).join(".git").exists();
+        if created {
+            git.git(&["init", "-q"])?;
+        }
+        git.exclude(index::FILE)?;
+        git.exclude(directory::TMP_FILES)?;
+        if created {
+            let mut paths = Vec::new();
+            for name in git.directory.names()? {
+                paths.extend(git.directory.watch_path(&name));
+                paths.push(git.directory.archive_path(&name));
+            }
+            git.commit(&paths, "Initial commit")?;
+        }
+
+        Ok(git)
+    }
+
+    /// Pulls the changes of the remote, rebasing the local ones on top, and
+    /// pushes the result.
+    pub fn sync(&self, remote: &str) -> Result<()> {
+        match self.git(&["remote", "get-url", REMOTE]) {
+            Ok(url) if url.trim() == remote => {}
+            Ok(_) => {
+                self.git(&["remote", "set-url", REMOTE, remote])?;
+            }
+            Err(_) => {
+                self.git(&["remote", "add", REMOTE, remote])?;
+            }
+        }
+
+        let branch = self.git(&["symbolic-ref", "--short", "HEAD"])?;
+        let branch = branch.trim();
+
+        // A new remote has nothing to pull yet
+        if self
+            .git(&["ls-remote", "--exit-code", "--heads", REMOTE, branch])
+            .is_ok()
+        {
+            if let Err(err) = self.git(&["pull", "-q", "--rebase", REMOTE, branch]) {
+                self.git(&["rebase", "--abort"]).ok();
+                return Err(err);
+            }
+        }
+
+        if self.git(&["rev-parse", "-q", "--verify", "HEAD"]).is_ok() {
+            let refspec = format!("HEAD:{}", branch);
+            self.git(&["push", "-q", REMOTE, refspec.as_str()])?;
+        }
+        Ok(())
+    }
+
+    /// The commits that changed a list or its archive, newest first.
+    pub fn history(&self, name: &str) -> Result<Vec<Commit>> {
+        if self.git(&["rev-parse", "-q", "--verify", "HEAD"]).is_err() {
+            return Ok(Vec::new());
+        }
+
+        let paths = [
+            format!("{}.json", name),
+            format!("{}.txt", name),
+            format!(".archive/{}.json", name),
+        ];
+        let mut args = vec![
+            "log",
+            "--date=format:%Y-%m-%d %H:%M",
+            "--format=%h%x1f%ad%x1f%s",
+            "--",
+        ];
+        args.extend(paths.iter().map(|path| path.as_str()));
+
+        let log = self.git(&args)?;
+        let commits = log
+            .lines()
+            .filter_map(|line| {
+                let mut fields = line.splitn(3, '\u{1f}');
+                Some(Commit {
+                    hash: fields.next()?.to_string(),
+                    date: fields.next()?.to_string(),
+                    message: fields.next()?.to_string(),
+                })
+            })
+            .collect();
+        Ok(commits)
+    }
+
+    /// Keeps a file of the todo path out of the commits.
+    fn exclude(&self, file: &str) -> Result<()> {
+        let path = Path::new(&self.path).join(".git/info/exclude");
+        let excluded = fs::read_to_string(&path).unwrap_or_default();
+        if excluded.lines().any(|line| line == file) {
+            return Ok(());
+        }
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).ok();
+        }
+        let data = format!("{}{}\n", excluded, file);
+        match fs::write(&path, data) {
+            Ok(_) => Ok(()),
+            Err(err) => Err(Error::io("write", path, err)),
+        }
+    }
+
+    /// Commits the changes of the given files, if there are any. Other
+    /// changes in the todo path are left to the user.
+    fn commit(&self, paths: &[PathBuf], message: &str) -> Result<()> {
+        let paths: Vec<String> = paths
+            .iter()
+            .map(|path| path.to_string_lossy().to_string())
+            .collect();
+        for path in paths.iter() {
+            if Path::new(path).exists() {
+                self.git(&["add", "--", path])?;
+            } else {
+                self.git(&["rm", "-q", "--cached", "--ignore-unmatch", "--", path])?;
+            }
+        }
+
+        let mut args = vec!["diff", "--cached", "--name-only", "-z", "--"];
+        args.extend(paths.iter().map(|path| path.as_str()));
+        let staged = self.git(&args)?;
+        let staged: Vec<&str> = staged.split('\0').filter(|path| !path.is_empty()).collect();
+        if staged.is_empty() {
+            return Ok(());
+        }
+
+        let mut args = vec!["commit", "-q", "-m", message, "--"];
+        args.extend(staged);
+        self.git(&args)?;
+        Ok(())
+    }
+
+    fn git(&self, args: &[&str]) -> Result<String> {
+        let mut command = Command::new("git");
+        command.arg("-C").arg(&self.path);
+        if !self.identity {
+            command.args([
+                "-c",
+                "user.name=rustodo",
+                "-c",
+                "user.email=rustodo@localhost",
+            ]);
+        }
+
+        let output = match command.args(args).output() {
+            Ok(output) => output,
+            Err(err) => return Err(Error::io("run", "git", err)),
+        };
+        if !output.status.success() {
+            return Err(Error::Git {
+                args: args.join(" "),
+                message: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            });
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
+}
+
+impl Storage for Git {
+    fn scan(&self) -> Result<(Vec<String>, Vec<String>)> {
+        self.directory.scan()
+    }
+
+    fn load(&self, name: &str) -> Result<TodoList> {
+        self.directory.load(name)
+    }
+
+    fn save(&self, list: &TodoList) -> Result<()> {
+        let old = self.directory.load(&list.name).ok();
+        self.directory.save(list)?;
+        self.commit(
+            &[self.directory.path(&list.name, list.format)],
+            &format!("lists/{}: {}", list.name, describe(old.as_ref(), list)),
+        )
+    }
+
+    fn rename(&self, name: &str, new_name: &str) -> Result<()> {
+        let mut paths = vec![
+            self.directory.archive_path(name),
+            self.directory.archive_path(new_name),
+        ];
+        paths.extend(self.directory.watch_path(name));
+        self.directory.rename(name, new_name)?;
+        paths.extend(self.directory.watch_path(new_name));
+        self.commit(
+            &paths,
+            &format!("lists/{}: renamed to '{}'", name, new_name),
+        )
+    }
+
+    fn delete(&self, name: &str) -> Result<()> {
+        let mut paths = vec![self.directory.archive_path(name)];
+        paths.extend(self.directory.watch_path(name));
+        self.directory.delete(name)?;
+        self.commit(&paths, &format!("lists/{}: removed", name))
+    }
+
+    fn summaries(&self) -> Result<(Vec<Summary>, Vec<String>)> {
+        self.directory.summaries()
+    }
+
+    fn modified(&self, name: &str) -> Option<u64> {
+        self.directory.modified(name)
+    }
+
+    fn load_archive(&self, name: &str) -> Result<TodoList> {
+        self.directory.load_archive(name)
+    }
+
+    fn save_archive(&self, archive: &TodoList) -> Result<()> {
+        let old = self.directory.load_archive(&archive.name)?;
+        self.directory.save_archive(archive)?;
+        let message = match archive.total().checked_sub(old.total()) {
+            Some(count) if count > 0 => format!("archived {} todos", count),
+            _ => describe(Some(&old), archive),
+        };
+        self.commit(
+            &[self.directory.archive_path(&archive.name)],
+            &format!("archive/{}: {}", archive.name, message),
+        )
+    }
+
+    fn watch_path(&self, name: &str) -> Option<PathBuf> {
+        self.directory.watch_path(name)
+    }
+}
+
+/// Describes the changes between two versions of a list, matching todos by id.
+fn describe(old: Option<&TodoList>, new: &TodoList) -> String {
+    let old = match old {
+        Some(old) => old,
+        None => return String::from("created"),
+    };
+
+    let mut changes = Vec::new();
+    for todo in new.todos.iter() {
+        let change = match old.todos.iter().find(|t| t.id == todo.id) {
+            None => "added",
+            Some(before) if !before.done && todo.done => "completed",
+            Some(before) if before.done && !todo.done => "reopened",
+            Some(before) if before.item != todo.item => "edited",
+            Some(before) if before.priority != todo.priority || before.tags != todo.tags => {
+                "updated"
+            }
+            Some(_) => continue,
+        };
+        changes.push(format!("{} {}", change, quote(todo)));
+    }
+    for todo in old.todos.iter() {
+        if !new.todos.iter().any(|t| t.id == todo.id) {
+            changes.push(format!("removed {}", quote(todo)));
+        }
+    }
+
+    let ids = |list: &TodoList| list.todos.iter().map(|t| t.id).collect::<Vec<i16>>();
+    match changes.len() {
+        0 if ids(old) != ids(new) => String::from("reordered"),
+        0 => String::from("saved"),
+        len if len <= MAX_CHANGES => changes.join(", "),
+        len => format!("{} changes", len),
+    }
+}
+
+fn quote(todo: &Todo) -> String {
+    let mut item: String = todo.item.chars().take(MAX_ITEM_LENGTH).collect();
+    if item.len() < todo.item.len() {
+        item.push_str("...");
+    }
+    format!("'{}'", item)
+}
@@ -0,0 +1,71 @@
+//! Summaries of the lists of a `Directory`, kept in `<todopath>/.rustodo.index`
+//! so `rustodo list` doesn't parse every file. An entry is used only while
+//! the size and modification time of its file are unchanged, the index is
+//! only a cache and is rebuilt from the lists when it is missing or broken.
+
+use super::Summary;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+
+pub const FILE: &str = ".rustodo.index";
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct Index {
+    lists: BTreeMap<String, Entry>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    stamp: Stamp,
+    summary: Summary,
+}
+
+/// Size and modification time in nanoseconds of a list file.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub struct Stamp(u64, u128);
+
+impl Stamp {
+    pub fn of(path: &Path) -> Option<Stamp> {
+        let metadata = fs::metadata(path).ok()?;
+        let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+        Some(Stamp(metadata.len(), modified.as_nanos()))
+    }
+}
+
+impl Index {
+    pub fn load(path: &Path) -> Self {
+        fs::read_to_string(path)
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default()
+    }
+
+    /// The summary of a list, if it was indexed with the file as it is now.
+    pub fn get(&self, name: &str, stamp: Stamp) -> Option<&Summary> {
+        self.lists
+            .get(name)
+            .filter(|entry| entry.stamp == stamp)
+            .map(|entry| &entry.summary)
+    }
+
+    /// Indexes the summary of the list stored under `name`, which is the
+    /// name of its file rather than the name the file holds.
+    pub fn insert(&mut self, name: &str, stamp: Stamp, summary: Summary) {
+        self.lists
+            .insert(name.to_string(), Entry { stamp, summary });
+    }
+
+    /// Drops the lists that aren't stored anymore. Returns whether any were.
+    pub fn retain(&mut self, names: &[String]) -> bool {
+        let len = self.lists.len();
+        self.lists.retain(|name, _| names.contains(name));
+        len != self.lists.len()
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+}
@@ -0,0 +1,342 @@
+//! All lists in one SQLite database, with indexes for queries across lists.
+//! Todos of a list and of its archive are rows of `todos`, their tags rows
+//! of `tags`. Removed lists keep their rows and get a `trashed_at` time.
+
+use super::{Query, Storage, Summary};
+use crate::date;
+use crate::error::{Error, Result};
+use crate::lists;
+use crate::todo::{Format, Todo, TodoList};
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use std::path::PathBuf;
+
+pub const DEFAULT_FILE: &str = "todos.sqlite";
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS lists (
+        id INTEGER PRIMARY KEY,
+        name TEXT NOT NULL,
+        modified_at INTEGER,
+        trashed_at INTEGER
+    );
+    CREATE UNIQUE INDEX IF NOT EXISTS lists_name ON lists (name) WHERE trashed_at IS NULL;
+    CREATE TABLE IF NOT EXISTS todos (
+        rowid INTEGER PRIMARY KEY,
+        list INTEGER NOT NULL REFERENCES lists (id),
+        archived INTEGER NOT NULL,
+        position INTEGER NOT NULL,
+        id INTEGER NOT NULL,
+        item TEXT NOT NULL,
+        priority INTEGER NOT NULL,
+        done INTEGER NOT NULL,
+        created_at INTEGER,
+        modified_at INTEGER,
+        completed_at INTEGER,
+        due_at INTEGER,
+        uid TEXT
+    );
+    CREATE INDEX IF NOT EXISTS todos_list ON todos (list, archived, position);
+    CREATE INDEX IF NOT EXISTS todos_done ON todos (done);
+    CREATE INDEX IF NOT EXISTS todos_due ON todos (due_at);
+    CREATE TABLE IF NOT EXISTS tags (
+        todo INTEGER NOT NULL REFERENCES todos (rowid),
+        tag TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS tags_todo ON tags (todo);
+    CREATE INDEX IF NOT EXISTS tags_tag ON tags (tag);
+";
+
+const TODO_COLUMNS: &str = "t.rowid, t.id, t.item, t.priority, t.done, t.created_at, \
+                            t.modified_at, t.completed_at, t.due_at, t.uid";
+
+pub struct Sqlite {
+    connection: Connection,
+    path: PathBuf,
+}
+
+impl Sqlite {
+    pub fn open(path: &str) -> Result<Self> {
+        let connection = Connection::open(path)?;
+        connection.execute_batch(SCHEMA)?;
+        Ok(Sqlite {
+            connection,
+            path: PathBuf::from(path),
+        })
+    }
+
+    fn list_id(&self, name: &str) -> Result<Option<i64>> {
+        let id = self
+            .connection
+            .query_row(
+                "SELECT id FROM lists WHERE name = ?1 AND trashed_at IS NULL",
+                params![name],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(id)
+    }
+
+    fn existing_list_id(&self, name: &str) -> Result<i64> {
+        match self.list_id(name)? {
+            Some(id) => Ok(id),
+            None => Err(Error::ListNotFound(name.to_string())),
+        }
+    }
+
+    fn load_todos(&self, list: i64, archived: bool) -> Result<Vec<Todo>> {
+        let mut statement = self.connection.prepare(&format!(
+            "SELECT {} FROM todos t WHERE t.list = ?1 AND t.archived = ?2 ORDER BY t.position",
+            TODO_COLUMNS
+        ))?;
+        let rows = statement.query_map(params![list, archived], |row| self.todo(row))?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    fn todo(&self, row: &Row) -> rusqlite::Result<Todo> {
+        let rowid: i64 = row.get(0)?;
+        let mut statement = self
+            .connection
+            .prepare_cached("SELECT tag FROM tags WHERE todo = ?1 ORDER BY rowid")?;
+        let tags = statement
+            .query_map(params![rowid], |row| row.get(0))?
+            .collect::<rusqlite::Result<_>>()?;
+
+        Ok(Todo {
+            id: row.get(1)?,
+            item: row.get(2)?,
+            priority: row.get(3)?,
+            tags,
+            done: row.get(4)?,
+            created_at: row.get(5)?,
+            modified_at: row.get(6)?,
+            completed_at: row.get(7)?,
+            due_at: row.get(8)?,
+            uid: row.get(9)?,
+        })
+    }
+
+    /// Replaces the todos of a list or of its archive, creating the list if
+    /// it doesn't exist yet.
+    fn save_todos(&self, list: &TodoList, archived: bool) -> Result<()> {
+        lists::validate(&list.name)?;
+        let transaction = self.connection.unchecked_transaction()?;
+        let now = date::now();
+
+        // An archive belongs to a list, saving one doesn't create the list
+        let id = match self.list_id(&list.name)? {
+            Some(id) => id,
+            None if archived => return Err(Error::ListNotFound(list.name.clone())),
+            None => {
+                transaction.execute(
+                    "INSERT INTO lists (name, modified_at) VALUES (?1, ?2)",
+                    params![list.name, now],
+                )?;
+                transaction.last_insert_rowid()
+            }
+        };
+
+        transaction.execute(
+            "DELETE FROM tags WHERE todo IN \
+             (SELECT rowid FROM todos WHERE list = ?1 AND archived = ?2)",
+            params![id, archived],
+        )?;
+        transaction.execute(
+            "DELETE FROM todos WHERE list = ?1 AND archived = ?2",
+            params![id, archived],
+        )?;
+
+        {
+            let mut insert_todo = transaction.prepare(
+                "INSERT INTO todos (list, archived, position, id, item, priority, done, \
+                 created_at, modified_at, completed_at, due_at, uid) \
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+            )?;
+            let mut insert_tag =
+                transaction.prepare("INSERT INTO tags (todo, tag) VALUES (?1, ?2)")?;
+
+            for (position, todo) in list.todos.iter().enumerate() {
+                insert_todo.execute(params![
+                    id,
+                    archived,
+                    position as i64,
+                    todo.id,
+                    todo.item,
+                    todo.priority,
+                    todo.done,
+                    todo.created_at,
+                    todo.modified_at,
+                    todo.completed_at,
+                    todo.due_at,
+                    todo.uid,
+                ])?;
+                let rowid = transaction.last_insert_rowid();
+                for tag in todo.tags.iter() {
+                    insert_tag.execute(params![rowid, tag])?;
+                }
+            }
+        }
+
+        if !archived {
+            transaction.execute(
+                "UPDATE lists SET modified_at = ?1 WHERE id = ?2",
+                params![now, id],
+            )?;
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+}
+
+impl Storage for Sqlite {
+    fn scan(&self) -> Result<(Vec<String>, Vec<String>)> {
+        let mut statement = self
+            .connection
+            .prepare("SELECT name FROM lists WHERE trashed_at IS NULL ORDER BY name")?;
+        let names = statement
+            .query_map([], |row| row.get(0))?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok((names, Vec::new()))
+    }
+
+    fn load(&self, name: &str) -> Result<TodoList> {
+        let id = self.existing_list_id(name)?;
+        let mut list = TodoList::new(name.to_string());
+        list.todos = self.load_todos(id, false)?;
+        Ok(list)
+    }
+
+    fn save(&self, list: &TodoList) -> Result<()> {
+        self.save_todos(list, false)
+    }
+
+    /// Counts the todos in the database instead of loading every list.
+    fn summaries(&self) -> Result<(Vec<Summary>, Vec<String>)> {
+        let mut statement = self.connection.prepare(
+            "SELECT l.name, l.modified_at, COUNT(t.rowid), COALESCE(SUM(t.done), 0), \
+             (SELECT GROUP_CONCAT(tag, char(10)) FROM \
+              (SELECT DISTINCT g.tag FROM tags g JOIN todos u ON u.rowid = g.todo \
+               WHERE u.list = l.id AND u.archived = 0 ORDER BY g.tag)) \
+             FROM lists l LEFT JOIN todos t ON t.list = l.id AND t.archived = 0 \
+             WHERE l.trashed_at IS NULL GROUP BY l.id ORDER BY l.name",
+        )?;
+        let rows = statement.query_map([], |row| {
+            let tags: Option<String> = row.get(4)?;
+            Ok(Summary {
+                name: row.get(0)?,
+                format: Format::Json,
+                done: row.get(3)?,
+                total: row.get(2)?,
+                tags: tags
+                    .map(|tags| tags.lines().map(String::from).collect())
+                    .unwrap_or_default(),
+                modified: row.get(1)?,
+            })
+        })?;
+        Ok((rows.collect::<rusqlite::Result<_>>()?, Vec::new()))
+    }
+
+    fn rename(&self, name: &str, new_name: &str) -> Result<()> {
+        lists::validate(new_name)?;
+        let id = self.existing_list_id(name)?;
+        self.connection.execute(
+            "UPDATE lists SET name = ?1, modified_at = ?2 WHERE id = ?3",
+            params![new_name, date::now(), id],
+        )?;
+        Ok(())
+    }
+
+    fn delete(&self, name: &str) -> Result<()> {
+        let id = self.existing_list_id(name)?;
+        self.connection.execute(
+            "UPDATE lists SET trashed_at = ?1 WHERE id = ?2",
+            params![date::now(), id],
+        )?;
+        Ok(())
+    }
+
+    fn modified(&self, name: &str) -> Option<u64> {
+        self.connection
+            .query_row(
+                "SELECT modified_at FROM lists WHERE name = ?1 AND trashed_at IS NULL",
+                params![name],
+                |row| row.get(0),
+            )
+            .ok()
+            .flatten()
+    }
+
+    fn load_archive(&self, name: &str) -> Result<TodoList> {
+        let mut archive = TodoList::new(name.to_string());
+        if let Some(id) = self.list_id(name)? {
+            archive.todos = self.load_todos(id, true)?;
+        }
+        Ok(archive)
+    }
+
+    fn save_archive(&self, archive: &TodoList) -> Result<()> {
+        self.save_todos(archive, true)
+    }
+
+    /// The database, which changes with any list.
+    fn watch_path(&self, _name: &str) -> Option<PathBuf> {
+        Some(self.path.clone())
+    }
+
+    /// Runs the query on the indexes instead of loading every list. Matching
+    /// text ignores the case of ASCII letters only.
+    fn find(&self, query: &Query) -> Result<Vec<(String, Todo)>> {
+        let mut statement = self.connection.prepare(&format!(
+            "SELECT {}, l.name FROM todos t JOIN lists l ON l.id = t.list \
+             WHERE l.trashed_at IS NULL AND t.archived = 0 \
+             AND (?1 IS NULL OR t.item LIKE '%' || ?1 || '%' ESCAPE '\\') \
+             AND (?2 IS NULL OR EXISTS (SELECT 1 FROM tags g WHERE g.todo = t.rowid AND g.tag = ?2)) \
+             AND (?3 IS NULL OR t.done = ?3) \
+             ORDER BY l.name, t.position",
+            TODO_COLUMNS
+        ))?;
+
+        let text = query.text.as_ref().map(|text| {
+            text.replace('\\', "\\\\")
+                .replace('%', "\\%")
+                .replace('_', "\\_")
+        });
+        let rows = statement.query_map(params![text, query.tag, query.done], |row| {
+            Ok((row.get(10)?, self.todo(row)?))
+        })?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn list(name: &str, items: &[&str]) -> TodoList {
+        let mut list = TodoList::new(name.to_string());
+        for item in items {
+            list.todos.push(Todo::new(list.next_id(), item.to_string()));
+        }
+        list
+    }
+
+    #[test]
+    fn keeps_lists_and_archives_apart() {
+        let storage = Sqlite::open(":memory:").unwrap();
+        storage.save(&list("home", &["a", "b"])).unwrap();
+        storage.save_archive(&list("home", &["c"])).unwrap();
+
+        assert_eq!(storage.load("home").unwrap().todos.len(), 2);
+        let archive = storage.load_archive("home").unwrap();
+        assert_eq!(archive.todos[0].item, "c");
+    }
+
+    #[test]
+    fn archive_of_a_missing_list_creates_no_list() {
+        let storage = Sqlite::open(":memory:").unwrap();
+        assert!(matches!(
+            storage.save_archive(&list("ghost", &["a"])),
+            Err(Error::ListNotFound(_))
+        ));
+        assert!(storage.names().unwrap().is_empty());
+    }
+}
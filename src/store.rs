@@ -0,0 +1,201 @@
+//! Operations on the lists of a storage backend that save the lists they
+//! change.
+
+use crate::error::{Error, Result};
+use crate::ical;
+use crate::lists;
+use crate::storage::Storage;
+use crate::todo::{Format, Todo, TodoList};
+use crate::todotxt;
+use std::fs;
+use std::path::Path;
+
+/// A stored list, with the time it was last modified.
+pub struct ListEntry {
+    pub list: TodoList,
+    pub modified: Option<u64>,
+}
+
+impl ListEntry {
+    pub fn open(&self) -> usize {
+        self.list.total() - self.list.completed()
+    }
+
+    pub fn progress(&self) -> f64 {
+        match self.list.total() {
+            0 => 0.0,
+            total => self.list.completed() as f64 / total as f64,
+        }
+    }
+}
+
+/// Creates and saves an empty list, replacing a list with the same name.
+pub fn create(storage: &dyn Storage, name: String) -> Result<TodoList> {
+    let created_list = TodoList::new(name);
+    storage.save(&created_list)?;
+    Ok(created_list)
+}
+
+/// Loads every list, returning warnings about the lists that couldn't be
+/// loaded.
+pub fn scan(storage: &dyn Storage) -> Result<(Vec<ListEntry>, Vec<String>)> {
+    let (names, mut warnings) = storage.scan()?;
+    let mut entries = Vec::new();
+
+    for name in names {
+        let list = match storage.load(&name) {
+            Ok(list) => list,
+            Err(err) => {
+                warnings.push(format!("Skipping '{}': {}", name, err));
+                continue;
+            }
+        };
+        let modified = storage.modified(&list.name);
+        entries.push(ListEntry { list, modified });
+    }
+
+    Ok((entries, warnings))
+}
+
+/// Imports a todo.txt file as a new list, or merges the todos of an
+/// iCalendar file into a (possibly existing) list. The list is named after
+/// the file unless a name is given. Returns the list and the number of
+/// imported todos.
+pub fn import(
+    storage: &dyn Storage,
+    file: &str,
+    name: Option<String>,
+) -> Result<(TodoList, usize)> {
+    let data = match fs::read_to_string(file) {
+        Ok(data) => data,
+        Err(err) => return Err(Error::io("read", file, err)),
+    };
+
+    let name = match name {
+        Some(name) => name.trim().to_string(),
+        None => match Path::new(file).file_stem() {
+            Some(stem) => stem.to_string_lossy().to_string(),
+            None => return Err(Error::InvalidName(file.to_string())),
+        },
+    };
+
+    // A list that can't be read mustn't be replaced by the import
+    let existing = match storage.load(&name) {
+        Ok(list) => Some(list),
+        Err(Error::ListNotFound(_)) => None,
+        Err(err) => return Err(err),
+    };
+
+    let (list, count) = match Path::new(file).extension() {
+        Some(extension) if extension == "ics" => {
+            let todos = ical::parse(data.as_str());
+            let count = todos.len();
+            let mut list = existing.unwrap_or_else(|| TodoList::new(name));
+            ical::merge(&mut list, todos);
+            (list, count)
+        }
+        _ => {
+            if existing.is_some() {
+                return Err(Error::ListExists(name));
+            }
+            let mut list = todotxt::parse(name, data.as_str());
+            list.format = Format::Json;
+            let count = list.total();
+            (list, count)
+        }
+    };
+
+    storage.save(&list)?;
+    Ok((list, count))
+}
+
+/// Moves the todo with the given id into another list, where it gets a
+/// fresh id. The target may be a unique prefix of a list name.
+pub fn move_todo(
+    storage: &dyn Storage,
+    source: &str,
+    id: i16,
+    target: &str,
+) -> Result<(Todo, TodoList)> {
+    let mut source = storage.load(source)?;
+    let names = storage.names()?;
+    let mut target = storage.load(&lists::complete(&names, target)?)?;
+
+    if source.name == target.name {
+        return Err(Error::SameList(target.name));
+    }
+
+    let index = match source.todos.iter().position(|todo| todo.id == id) {
+        Some(index) => index,
+        None => {
+            return Err(Error::TodoNotFound {
+                list: source.name,
+                id,
+            })
+        }
+    };
+    let todo = source.todos.remove(index);
+    target.append(vec![todo.clone()]);
+
+    // The target is written first, so a failure can't lose the todo
+    storage.save(&target)?;
+    storage.save(&source)?;
+
+    Ok((todo, target))
+}
+
+/// Renames a list (and its archive). Returns the old name and the renamed
+/// list.
+pub fn rename(storage: &dyn Storage, name: &str, new_name: String) -> Result<(String, TodoList)> {
+    let mut list = storage.load(name)?;
+    ensure_missing(storage, &new_name)?;
+
+    storage.rename(&list.name, &new_name)?;
+    let old_name = std::mem::replace(&mut list.name, new_name);
+
+    Ok((old_name, list))
+}
+
+/// Removes a list (and its archive), see `Storage::delete`.
+pub fn remove(storage: &dyn Storage, name: &str) -> Result<TodoList> {
+    let list = storage.load(name)?;
+    storage.delete(&list.name)?;
+    Ok(list)
+}
+
+/// Saves a copy of a list under a new name.
+pub fn copy(storage: &dyn Storage, name: &str, new_name: String) -> Result<TodoList> {
+    let mut list = storage.load(name)?;
+    ensure_missing(storage, &new_name)?;
+
+    list.name = new_name;
+    for todo in list.todos.iter_mut() {
+        // A copy is a new calendar entry
+        todo.uid = None;
+    }
+    storage.save(&list)?;
+
+    Ok(list)
+}
+
+/// Unchecks every todo, so a checklist can be reused. Returns the list and
+/// the number of unchecked todos.
+pub fn reset(storage: &dyn Storage, name: &str) -> Result<(TodoList, usize)> {
+    let mut list = storage.load(name)?;
+    let mut count = 0;
+
+    for todo in list.todos.iter_mut().filter(|todo| todo.done) {
+        todo.toggle();
+        count += 1;
+    }
+    storage.save(&list)?;
+
+    Ok((list, count))
+}
+
+fn ensure_missing(storage: &dyn Storage, name: &str) -> Result<()> {
+    if storage.exists(name)? {
+        return Err(Error::ListExists(name.to_string()));
+    }
+    Ok(())
+}
@@ -0,0 +1,96 @@
+//! Leaving the terminal usable when the panel stops unexpectedly: a panic
+//! hook and handlers for the signals that end or suspend the process, which
+//! turn into `Event::Interrupt` and `Event::Suspend`. Resizes of the terminal
+//! are sent as `Event::Resize`.
+
+use crate::draw;
+use crate::panel::Event;
+use signal_hook::consts::{SIGCONT, SIGHUP, SIGINT, SIGTERM, SIGTSTP, SIGWINCH};
+use signal_hook::iterator::Signals;
+use std::io::{stdout, Write};
+use std::mem::MaybeUninit;
+use std::sync::mpsc::Sender;
+use std::sync::OnceLock;
+use std::{panic, process, thread};
+
+/// Keys that always quit or suspend the panel, the signals they send in
+/// cooked mode aren't sent in raw mode.
+pub const INTERRUPT_KEY: char = 'c';
+pub const SUSPEND_KEY: char = 'z';
+
+/// Exit status after a panic, as the default panic handler uses.
+const PANIC_STATUS: i32 = 101;
+
+/// Settings of the terminal before the panel changed them.
+static ORIGINAL: OnceLock<libc::termios> = OnceLock::new();
+
+/// Remembers the terminal settings, which must happen before raw mode is
+/// entered, and installs the panic hook and signal handlers.
+pub fn install(events: Sender<Event>) {
+    let mut termios = MaybeUninit::<libc::termios>::uninit();
+    // SAFETY: tcgetattr fills the struct when it succeeds
+    if unsafe { libc::tcgetattr(libc::STDIN_FILENO, termios.as_mut_ptr()) } == 0 {
+        ORIGINAL.get_or_init(|| unsafe { termios.assume_init() });
+    }
+
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        restore();
+        default_hook(info);
+        // A panic of the reader or the watcher would leave the panel waiting
+        process::exit(PANIC_STATUS);
+    }));
+
+    let mut signals = match Signals::new([SIGINT, SIGTERM, SIGHUP, SIGTSTP, SIGCONT, SIGWINCH]) {
+        Ok(signals) => signals,
+        Err(err) => {
+            events.send(Event::IoError(err.to_string())).ok();
+            return;
+        }
+    };
+    thread::spawn(move || {
+        for signal in signals.forever() {
+            let event = match signal {
+                SIGTSTP => Event::Suspend,
+                // Continued after a stop the panel didn't ask for
+                SIGCONT => Event::Resume,
+                SIGWINCH => Event::Resize,
+                signal => Event::Interrupt(signal),
+            };
+            if events.send(event).is_err() {
+                return;
+            }
+        }
+    });
+}
+
+/// Shows the cursor, stops mouse reports, leaves the alternate screen and
+/// restores the remembered terminal settings.
+pub fn restore() {
+    let mut out = stdout();
+    write!(
+        out,
+        "{}{}{}",
+        draw::disable_mouse(),
+        draw::show_cursor(),
+        draw::main_screen()
+    )
+    .and_then(|_| out.flush())
+    .ok();
+
+    if let Some(termios) = ORIGINAL.get() {
+        // SAFETY: the struct was filled by tcgetattr
+        unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, termios) };
+    }
+}
+
+/// Stops the process like the default action of SIGTSTP would, returning
+/// once it is continued.
+pub fn stop() {
+    signal_hook::low_level::raise(libc::SIGSTOP).ok();
+}
+
+/// Exits like the default action of a terminating signal would.
+pub fn exit(signal: i32) -> ! {
+    process::exit(128 + signal)
+}
@@ -0,0 +1,27 @@
+//! Fixtures shared by the tests of the library and of the binary, which
+//! both declare this module.
+
+use std::fs;
+use std::path::PathBuf;
+use std::process;
+
+/// An empty todo path, removed when the test ends.
+pub struct TodoPath(pub PathBuf);
+
+impl TodoPath {
+    pub fn new(test: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("rustodo-{}-{}", process::id(), test));
+        fs::create_dir_all(&path).unwrap();
+        TodoPath(path)
+    }
+
+    pub fn path(&self) -> &str {
+        self.0.to_str().unwrap()
+    }
+}
+
+impl Drop for TodoPath {
+    fn drop(&mut self) {
+        fs::remove_dir_all(&self.0).ok();
+    }
+}
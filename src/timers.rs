@@ -0,0 +1,93 @@
+//! Deadlines the panel's loop wakes up for between events, so timed work
+//! doesn't need threads of its own.
+
+use std::mem;
+use std::time::{Duration, Instant};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Timer {
+    /// The message with the given id was shown for its duration.
+    Expire(u64),
+    /// Saves the list if it has changes, see `Settings::autosave`.
+    Autosave,
+    /// Start of the next minute, which moves the clock and may make todos
+    /// due.
+    Tick,
+}
+
+#[derive(Default)]
+pub struct Timers {
+    pending: Vec<(Instant, Timer)>,
+}
+
+impl Timers {
+    /// Starts a timer, replacing a pending timer of the same kind.
+    pub fn set(&mut self, timer: Timer, after: Duration) {
+        self.pending
+            .retain(|(_, t)| mem::discriminant(t) != mem::discriminant(&timer));
+        self.pending.push((Instant::now() + after, timer));
+    }
+
+    /// When the earliest pending timer is due.
+    pub fn next(&self) -> Option<Instant> {
+        self.pending.iter().map(|(deadline, _)| *deadline).min()
+    }
+
+    /// Removes the timers that are due, earliest first.
+    pub fn due(&mut self, now: Instant) -> Vec<Timer> {
+        let mut due: Vec<(Instant, Timer)> = Vec::new();
+        self.pending.retain(|&(deadline, timer)| {
+            if deadline <= now {
+                due.push((deadline, timer));
+                false
+            } else {
+                true
+            }
+        });
+        due.sort_by_key(|(deadline, _)| *deadline);
+        due.into_iter().map(|(_, timer)| timer).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECOND: Duration = Duration::from_secs(1);
+
+    #[test]
+    fn returns_due_timers_earliest_first() {
+        let mut timers = Timers::default();
+        let start = Instant::now();
+        timers.set(Timer::Autosave, 3 * SECOND);
+        timers.set(Timer::Tick, SECOND);
+        timers.set(Timer::Expire(1), 2 * SECOND);
+
+        assert!(timers.next().unwrap() <= start + 2 * SECOND);
+        assert!(timers.due(start).is_empty());
+        assert_eq!(
+            timers.due(start + 5 * SECOND),
+            [Timer::Tick, Timer::Expire(1), Timer::Autosave]
+        );
+        assert_eq!(timers.next(), None);
+    }
+
+    #[test]
+    fn keeps_timers_that_arent_due() {
+        let mut timers = Timers::default();
+        timers.set(Timer::Tick, SECOND);
+        timers.set(Timer::Autosave, 60 * SECOND);
+
+        assert_eq!(timers.due(Instant::now() + 2 * SECOND), [Timer::Tick]);
+        assert!(timers.next().is_some());
+    }
+
+    #[test]
+    fn setting_a_timer_replaces_one_of_the_same_kind() {
+        let mut timers = Timers::default();
+        timers.set(Timer::Expire(1), SECOND);
+        timers.set(Timer::Expire(2), 2 * SECOND);
+
+        assert_eq!(timers.due(Instant::now() + 3 * SECOND), [Timer::Expire(2)]);
+    }
+}
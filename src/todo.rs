@@ -1,10 +1,33 @@
+//! Todo lists and their items.
+
+use crate::date;
+use crate::error::{Error, Result};
+use crate::todotxt;
 use serde::{Deserialize, Serialize};
-use std::fs;
 
-#[derive(Serialize, Deserialize, Debug)]
+/// On-disk representation of a todo list inside the todo path.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Format {
+    #[default]
+    Json,
+    TodoTxt,
+}
+
+impl Format {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Format::Json => "json",
+            Format::TodoTxt => "txt",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct TodoList {
     pub name: String,
     pub todos: Vec<Todo>,
+    #[serde(skip)]
+    pub format: Format,
 }
 
 impl TodoList {
@@ -12,51 +35,191 @@ impl TodoList {
         TodoList {
             name,
             todos: Vec::new(),
+            format: Format::Json,
         }
     }
 
     pub fn to_json(&self) -> String {
-        return serde_json::to_string(self).expect("Error serializing json");
+        serde_json::to_string(self).expect("Error serializing json")
     }
 
-    pub fn save(&self, dir_path: &String) -> Result<(), String> {
-        let json = self.to_json();
-        let path = format!("{}/{}.json", dir_path, self.name);
-
-        match fs::write(&path, &json) {
-            Ok(_) => Ok(()),
-            Err(err) => Err(err.to_string()),
+    /// The list as it is written in its format. Two lists with the same
+    /// serialization are stored the same, a todo.txt file doesn't keep every
+    /// field of a todo.
+    pub fn serialize(&self) -> String {
+        match self.format {
+            Format::Json => self.to_json(),
+            Format::TodoTxt => todotxt::serialize(self),
         }
     }
 
     pub fn completed(&self) -> usize {
-        let mut completed = 0;
-
-        for item in self.todos.to_owned() {
-            if item.done {
-                completed += 1;
-            }
-        }
-
-        completed
+        self.todos.iter().filter(|todo| todo.done).count()
     }
 
     pub fn total(&self) -> usize {
         self.todos.len()
     }
+
+    /// Id for a new todo, one above the highest id in the list.
+    pub fn next_id(&self) -> i16 {
+        self.todos.iter().map(|todo| todo.id).max().unwrap_or(0) + 1
+    }
+
+    /// Adds a todo with the given fields, which must include the item.
+    /// Returns the index of the todo.
+    pub fn add(&mut self, changes: TodoChanges) -> Result<usize> {
+        let mut todo = Todo::new(self.next_id(), String::new());
+        if changes.item.is_none() {
+            return Err(Error::InvalidTodo(String::from("a todo needs an item")));
+        }
+        todo.apply(changes)?;
+        self.todos.push(todo);
+        Ok(self.todos.len() - 1)
+    }
+
+    /// Appends todos from another list, giving each a fresh id.
+    pub fn append(&mut self, todos: Vec<Todo>) {
+        for mut todo in todos {
+            todo.id = self.next_id();
+            self.todos.push(todo);
+        }
+    }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
 pub struct Todo {
     pub id: i16,
     pub item: String,
+    /// 0 means no priority, 1 is the highest (todo.txt `(A)`) and 26 the lowest (`(Z)`).
     pub priority: i8,
     pub tags: Vec<String>,
     pub done: bool,
+    /// Unix timestamps in seconds.
+    #[serde(default)]
+    pub created_at: Option<u64>,
+    #[serde(default)]
+    pub modified_at: Option<u64>,
+    #[serde(default)]
+    pub completed_at: Option<u64>,
+    #[serde(default)]
+    pub due_at: Option<u64>,
+    /// Identifier of the calendar entry the item was imported from.
+    #[serde(default)]
+    pub uid: Option<String>,
 }
 
 impl Todo {
+    /// A new open todo, created now. See `TodoList::next_id` for the id.
+    pub fn new(id: i16, item: String) -> Self {
+        let now = date::now();
+        Todo {
+            id,
+            item,
+            created_at: Some(now),
+            modified_at: Some(now),
+            ..Default::default()
+        }
+    }
+
+    pub fn set_item(&mut self, item: String) {
+        self.item = item;
+        self.modified_at = Some(date::now());
+    }
+
+    pub fn set_priority(&mut self, priority: i8) {
+        self.priority = priority;
+        self.modified_at = Some(date::now());
+    }
+
+    pub fn add_tag(&mut self, tag: &str) {
+        if !self.tags.iter().any(|t| t == tag) {
+            self.tags.push(tag.to_string());
+            self.modified_at = Some(date::now());
+        }
+    }
+
+    pub fn remove_tag(&mut self, tag: &str) {
+        let len = self.tags.len();
+        self.tags.retain(|t| t != tag);
+        if self.tags.len() != len {
+            self.modified_at = Some(date::now());
+        }
+    }
+
+    /// Checks or unchecks the todo, recording when it was completed.
     pub fn toggle(&mut self) {
+        let now = date::now();
         self.done = !self.done;
+        self.completed_at = if self.done { Some(now) } else { None };
+        self.modified_at = Some(now);
+    }
+}
+
+/// Changes to the fields of a todo, unset fields are kept. Used by clients
+/// that send todos as json, so the fields are named like those of `Todo`.
+#[derive(Deserialize, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct TodoChanges {
+    pub item: Option<String>,
+    pub priority: Option<i8>,
+    /// Replaces all tags.
+    pub tags: Option<Vec<String>>,
+    pub done: Option<bool>,
+    pub due_at: Option<Option<u64>>,
+}
+
+impl Todo {
+    /// Applies changes, checking them all before changing anything.
+    pub fn apply(&mut self, changes: TodoChanges) -> Result<()> {
+        if changes
+            .item
+            .as_ref()
+            .is_some_and(|item| item.trim().is_empty())
+        {
+            return Err(Error::InvalidTodo(String::from("a todo needs an item")));
+        }
+        if let Some(priority) = changes.priority.filter(|p| !(0..=26).contains(p)) {
+            return Err(Error::InvalidTodo(format!(
+                "invalid priority {} (expected 0 to 26)",
+                priority
+            )));
+        }
+
+        if let Some(item) = changes.item {
+            self.set_item(item.trim().to_string());
+        }
+        if let Some(priority) = changes.priority {
+            self.set_priority(priority);
+        }
+        if let Some(tags) = changes.tags {
+            for tag in self.tags.clone() {
+                self.remove_tag(&tag);
+            }
+            for tag in tags {
+                self.add_tag(&tag);
+            }
+        }
+        if let Some(done) = changes.done {
+            if self.done != done {
+                self.toggle();
+            }
+        }
+        if let Some(due_at) = changes.due_at {
+            self.due_at = due_at;
+            self.modified_at = Some(date::now());
+        }
+        Ok(())
+    }
+}
+
+/// Parses a priority given either as a todo.txt letter (`A` is the highest)
+/// or as a number. An empty text or `0` means no priority.
+pub fn parse_priority(text: &str) -> Option<i8> {
+    let text = text.trim();
+    match text.as_bytes() {
+        [] => Some(0),
+        [c @ b'a'..=b'z'] | [c @ b'A'..=b'Z'] => Some((c.to_ascii_uppercase() - b'A') as i8 + 1),
+        _ => text.parse().ok().filter(|p| (0..=26).contains(p)),
     }
 }
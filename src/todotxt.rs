@@ -0,0 +1,276 @@
+//! Reading and writing of the todo.txt format, see
+//! https://github.com/todotxt/todo.txt for the specification.
+//!
+//! `+project` and `@context` words are kept inside the item text and are
+//! additionally collected into `Todo::tags` (including their sigil), so a
+//! list survives a round trip unchanged. Completed items can't carry a
+//! `(A)` priority, so it is written as a `pri:A` tag instead. The UID of an
+//! item exported to a calendar is kept as a `uid:` tag.
+
+use crate::date;
+use crate::todo::{Format, Todo, TodoList};
+
+pub fn parse(name: String, data: &str) -> TodoList {
+    let mut list = TodoList::new(name);
+    list.format = Format::TodoTxt;
+
+    for line in data.lines() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let todo = parse_todo(list.next_id(), line);
+        list.todos.push(todo);
+    }
+
+    list
+}
+
+pub fn serialize(list: &TodoList) -> String {
+    let mut out = String::new();
+    for todo in list.todos.iter() {
+        out.push_str(format_todo(todo).as_str());
+        out.push('\n');
+    }
+    out
+}
+
+pub fn parse_todo(id: i16, line: &str) -> Todo {
+    let mut rest = line.trim();
+    let mut done = false;
+    let mut priority = 0;
+    let mut completed_at = None;
+    let mut created_at = None;
+
+    if let Some(stripped) = rest.strip_prefix("x ") {
+        done = true;
+        rest = stripped.trim_start();
+    }
+
+    if let Some((p, stripped)) = take_priority(rest) {
+        priority = p;
+        rest = stripped;
+    }
+
+    if let Some((timestamp, stripped)) = take_date(rest) {
+        rest = stripped;
+        // Completed items list the completion date first, followed by the
+        // optional creation date.
+        if done {
+            completed_at = Some(timestamp);
+            if let Some((timestamp, stripped)) = take_date(rest) {
+                created_at = Some(timestamp);
+                rest = stripped;
+            }
+        } else {
+            created_at = Some(timestamp);
+        }
+    }
+
+    let mut words = Vec::new();
+    let mut tags = Vec::new();
+    let mut uid = None;
+    for word in rest.split_whitespace() {
+        if let Some(value) = word.strip_prefix("uid:").filter(|value| !value.is_empty()) {
+            uid = Some(value.to_string());
+            continue;
+        }
+        if let Some(p) = word.strip_prefix("pri:").and_then(priority_from_letter) {
+            if priority == 0 {
+                priority = p;
+            }
+            continue;
+        }
+        if is_tag(word) && !tags.iter().any(|t| t == word) {
+            tags.push(word.to_string());
+        }
+        words.push(word);
+    }
+
+    Todo {
+        id,
+        item: words.join(" "),
+        priority,
+        tags,
+        done,
+        created_at,
+        completed_at,
+        uid,
+        ..Default::default()
+    }
+}
+
+pub fn format_todo(todo: &Todo) -> String {
+    let mut parts: Vec<String> = Vec::new();
+    let priority = priority_to_letter(todo.priority);
+
+    if todo.done {
+        parts.push("x".into());
+        // A lone date after `x` is read back as the completion date, so the
+        // creation date can only be written alongside a completion date. The
+        // last change stands in for a completion date that wasn't recorded.
+        let completed_at = todo.completed_at.or_else(|| {
+            todo.created_at
+                .map(|created_at| todo.modified_at.unwrap_or(created_at))
+        });
+        if let Some(completed_at) = completed_at {
+            parts.push(date::format_date(completed_at));
+            if let Some(created_at) = todo.created_at {
+                parts.push(date::format_date(created_at));
+            }
+        }
+    } else {
+        if let Some(letter) = priority {
+            parts.push(format!("({})", letter));
+        }
+        if let Some(created_at) = todo.created_at {
+            parts.push(date::format_date(created_at));
+        }
+    }
+
+    parts.push(todo.item.clone());
+
+    for tag in todo.tags.iter() {
+        let tag = if is_tag(tag) {
+            tag.clone()
+        } else {
+            format!("+{}", tag.replace(char::is_whitespace, "_"))
+        };
+        if !todo.item.split_whitespace().any(|word| word == tag) {
+            parts.push(tag);
+        }
+    }
+
+    if todo.done {
+        if let Some(letter) = priority {
+            parts.push(format!("pri:{}", letter));
+        }
+    }
+
+    // A UID with spaces can't be a tag, calendars rarely make those
+    if let Some(uid) = todo
+        .uid
+        .as_ref()
+        .filter(|uid| !uid.contains(char::is_whitespace))
+    {
+        parts.push(format!("uid:{}", uid));
+    }
+
+    parts.join(" ")
+}
+
+fn is_tag(word: &str) -> bool {
+    word.len() > 1 && (word.starts_with('+') || word.starts_with('@'))
+}
+
+fn take_priority(text: &str) -> Option<(i8, &str)> {
+    let bytes = text.as_bytes();
+    if bytes.len() < 4 || bytes[0] != b'(' || bytes[2] != b')' || bytes[3] != b' ' {
+        return None;
+    }
+    let priority = priority_from_letter(&text[1..2])?;
+    Some((priority, text[4..].trim_start()))
+}
+
+fn take_date(text: &str) -> Option<(u64, &str)> {
+    let (word, rest) = text.split_once(' ').unwrap_or((text, ""));
+    let timestamp = date::parse_date(word)?;
+    Some((timestamp, rest.trim_start()))
+}
+
+fn priority_from_letter(letter: &str) -> Option<i8> {
+    match letter.as_bytes() {
+        [c @ b'A'..=b'Z'] => Some((c - b'A') as i8 + 1),
+        _ => None,
+    }
+}
+
+fn priority_to_letter(priority: i8) -> Option<char> {
+    if (1..=26).contains(&priority) {
+        Some((b'A' + priority as u8 - 1) as char)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LINES: &str = "\
+(A) 2024-01-02 call mom +family @phone
+x 2024-01-05 2024-01-03 pay rent +home pri:B
+x 2024-01-04 buy milk
+plain item
+";
+
+    fn day(text: &str) -> Option<u64> {
+        date::parse_date(text)
+    }
+
+    #[test]
+    fn parses_priority_dates_and_tags() {
+        let list = parse(String::from("test"), LINES);
+        assert_eq!(list.todos.len(), 4);
+
+        let todo = &list.todos[0];
+        assert_eq!((todo.priority, todo.done), (1, false));
+        assert_eq!(todo.created_at, day("2024-01-02"));
+        assert_eq!(todo.item, "call mom +family @phone");
+        assert_eq!(todo.tags, ["+family", "@phone"]);
+
+        let todo = &list.todos[1];
+        assert_eq!((todo.priority, todo.done), (2, true));
+        assert_eq!(todo.completed_at, day("2024-01-05"));
+        assert_eq!(todo.created_at, day("2024-01-03"));
+        assert_eq!(todo.item, "pay rent +home");
+
+        let todo = &list.todos[2];
+        assert_eq!(todo.completed_at, day("2024-01-04"));
+        assert_eq!(todo.created_at, None);
+    }
+
+    #[test]
+    fn round_trips() {
+        let list = parse(String::from("test"), LINES);
+        assert_eq!(serialize(&list), LINES);
+    }
+
+    #[test]
+    fn writes_tags_that_arent_in_the_item() {
+        let todo = Todo {
+            item: String::from("water plants"),
+            tags: vec![String::from("garden work"), String::from("@home")],
+            ..Default::default()
+        };
+        assert_eq!(format_todo(&todo), "water plants +garden_work @home");
+    }
+
+    #[test]
+    fn keeps_uids() {
+        let todo = parse_todo(1, "call mom uid:abc-1@rustodo +family");
+        assert_eq!(todo.uid.as_deref(), Some("abc-1@rustodo"));
+        assert_eq!(todo.item, "call mom +family");
+        assert_eq!(format_todo(&todo), "call mom +family uid:abc-1@rustodo");
+    }
+
+    #[test]
+    fn keeps_the_creation_date_without_a_completion_date() {
+        let todo = Todo {
+            item: String::from("done"),
+            done: true,
+            created_at: day("2024-01-02"),
+            modified_at: day("2024-01-06"),
+            ..Default::default()
+        };
+        assert_eq!(format_todo(&todo), "x 2024-01-06 2024-01-02 done");
+
+        let todo = Todo {
+            modified_at: None,
+            ..todo
+        };
+        assert_eq!(format_todo(&todo), "x 2024-01-02 2024-01-02 done");
+
+        let parsed = parse_todo(1, &format_todo(&todo));
+        assert_eq!(parsed.created_at, day("2024-01-02"));
+    }
+}